@@ -0,0 +1,71 @@
+use crate::name::Crate;
+use crate::Configs;
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+// clippy.toml, .clippy.toml
+pub(crate) fn options(contents: &str) -> Result<Vec<(String, String)>, toml::de::Error> {
+    let table: toml::Table = toml::from_str(contents)?;
+    let options = table
+        .into_iter()
+        .map(|(option, value)| (option.replace('_', "-"), value.to_string()))
+        .collect();
+    Ok(options)
+}
+
+// Record the value that the crate sets for each option.
+pub(crate) fn tally(configs: &mut Configs, krate: &Crate, options: Vec<(String, String)>) {
+    for (option, value) in options {
+        let values = configs.entry(option).or_insert_with(Map::new);
+        values.insert(krate.clone(), value);
+    }
+}
+
+// Crates which set any clippy.toml option that controls the given lint.
+pub(crate) fn crates_tuning<'a>(lint_id: &str, configs: &'a Configs) -> Set<&'a Crate> {
+    let mut crates = Set::new();
+    for (option, values) in configs {
+        if option_lints(option).contains(&lint_id) {
+            crates.extend(values.keys());
+        }
+    }
+    crates
+}
+
+fn option_lints(option: &str) -> &'static [&'static str] {
+    match option {
+        "allow-dbg-in-tests" => &["dbg_macro"],
+        "allow-expect-in-tests" => &["expect_used"],
+        "allow-print-in-tests" => &["print_stderr", "print_stdout"],
+        "allow-unwrap-in-tests" => &["unwrap_used"],
+        "array-size-threshold" => &["large_const_arrays", "large_stack_arrays"],
+        "cognitive-complexity-threshold" => &["cognitive_complexity"],
+        "disallowed-macros" => &["disallowed_macros"],
+        "disallowed-methods" => &["disallowed_methods"],
+        "disallowed-names" => &["disallowed_names"],
+        "disallowed-types" => &["disallowed_types"],
+        "doc-valid-idents" => &["doc_markdown"],
+        "enum-variant-name-threshold" => &["enum_variant_names"],
+        "enum-variant-size-threshold" => &["large_enum_variant"],
+        "excessive-nesting-threshold" => &["excessive_nesting"],
+        "future-size-threshold" => &["large_futures"],
+        "large-error-threshold" => &["result_large_err"],
+        "literal-representation-threshold" => &["decimal_literal_representation"],
+        "max-fn-params-bools" => &["fn_params_excessive_bools"],
+        "max-include-file-size" => &["large_include_file"],
+        "max-struct-bools" => &["struct_excessive_bools"],
+        "max-trait-bounds" => &["type_repetition_in_bounds"],
+        "min-ident-chars-threshold" => &["min_ident_chars"],
+        "pass-by-value-size-limit" => &["large_types_passed_by_value"],
+        "single-char-binding-names-threshold" => &["many_single_char_names"],
+        "stack-size-threshold" => &["large_stack_frames"],
+        "too-large-for-stack" => &["boxed_local", "useless_vec"],
+        "too-many-arguments-threshold" => &["too_many_arguments"],
+        "too-many-lines-threshold" => &["too_many_lines"],
+        "trivial-copy-size-limit" => &["trivially_copy_pass_by_ref"],
+        "type-complexity-threshold" => &["type_complexity"],
+        "unreadable-literal-lint-fractions" => &["unreadable_literal"],
+        "upper-case-acronyms-aggressive" => &["upper_case_acronyms"],
+        "vec-box-size-threshold" => &["vec_box"],
+        _ => &[],
+    }
+}
@@ -0,0 +1,243 @@
+use crate::name::Crate;
+use anyhow::{bail, Result};
+use semver::Version;
+use serde_derive::Deserialize;
+use std::collections::btree_map::{BTreeMap as Map, Entry};
+use std::collections::BTreeSet as Set;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+// Publish date and status of every version on crates.io, and how widely used
+// each crate is, from the tables in https://static.crates.io/db-dump.tar.gz.
+pub(crate) struct DbDump {
+    pub releases: Map<Crate, Map<Version, Release>>,
+    pub popularity: Map<Crate, Popularity>,
+}
+
+pub(crate) struct Release {
+    pub created_at: Date,
+    pub yanked: bool,
+}
+
+#[derive(Copy, Clone, Default)]
+pub(crate) struct Popularity {
+    pub downloads: u64,
+    // Other crates whose newest non-yanked version has a normal or build
+    // dependency on this crate.
+    pub dependents: u64,
+}
+
+#[derive(Deserialize)]
+struct CrateRow {
+    id: u64,
+    name: String,
+    // Moved to a separate table in 2024.
+    #[serde(default)]
+    downloads: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct CrateDownloadsRow {
+    crate_id: u64,
+    downloads: u64,
+}
+
+#[derive(Deserialize)]
+struct VersionRow {
+    id: u64,
+    crate_id: u64,
+    num: String,
+    created_at: String,
+    yanked: String,
+}
+
+#[derive(Deserialize)]
+struct DependencyRow {
+    version_id: u64,
+    crate_id: u64,
+    kind: u8,
+}
+
+// The directory extracted from the db dump tarball, or the data directory
+// inside of it.
+pub(crate) fn load(dir: &Path) -> Result<DbDump> {
+    let data_dir = if dir.join("data").is_dir() {
+        dir.join("data")
+    } else {
+        dir.to_owned()
+    };
+
+    let mut crate_names = Map::new();
+    let mut downloads = Map::new();
+    for row in csv::Reader::from_path(table(&data_dir, "crates")?)?.deserialize() {
+        let row: CrateRow = row?;
+        crate_names.insert(row.id, Crate::new(row.name));
+        if let Some(count) = row.downloads {
+            downloads.insert(row.id, count);
+        }
+    }
+    let crate_downloads = data_dir.join("crate_downloads.csv");
+    if crate_downloads.is_file() {
+        for row in csv::Reader::from_path(crate_downloads)?.deserialize() {
+            let row: CrateDownloadsRow = row?;
+            downloads.insert(row.crate_id, row.downloads);
+        }
+    }
+
+    let mut releases = Map::new();
+    // The newest non-yanked version of each crate, by crate id.
+    let mut newest = Map::new();
+    for row in csv::Reader::from_path(table(&data_dir, "versions")?)?.deserialize() {
+        let row: VersionRow = row?;
+        let Some(krate) = crate_names.get(&row.crate_id) else {
+            continue;
+        };
+        let Ok(version) = Version::parse(&row.num) else {
+            continue;
+        };
+        let release = Release {
+            created_at: row.created_at.parse()?,
+            yanked: row.yanked == "t",
+        };
+        if !release.yanked {
+            match newest.entry(row.crate_id) {
+                Entry::Vacant(entry) => {
+                    entry.insert((version.clone(), row.id));
+                }
+                Entry::Occupied(mut entry) => {
+                    if version > entry.get().0 {
+                        entry.insert((version.clone(), row.id));
+                    }
+                }
+            }
+        }
+        releases
+            .entry(krate.clone())
+            .or_insert_with(Map::new)
+            .insert(version, release);
+    }
+
+    let newest_version_ids = newest
+        .into_iter()
+        .map(|(crate_id, (_version, version_id))| (version_id, crate_id))
+        .collect::<Map<_, _>>();
+    let mut dependents = Map::new();
+    for row in csv::Reader::from_path(table(&data_dir, "dependencies")?)?.deserialize() {
+        let row: DependencyRow = row?;
+        // Dev-dependencies do not make the crate part of anyone else's build.
+        if row.kind == DEV_DEPENDENCY {
+            continue;
+        }
+        if let Some(dependent) = newest_version_ids.get(&row.version_id) {
+            dependents
+                .entry(row.crate_id)
+                .or_insert_with(Set::new)
+                .insert(*dependent);
+        }
+    }
+
+    let mut popularity = Map::new();
+    for (crate_id, krate) in &crate_names {
+        let entry = Popularity {
+            downloads: downloads.get(crate_id).copied().unwrap_or(0),
+            dependents: dependents.get(crate_id).map_or(0, |set| set.len() as u64),
+        };
+        popularity.insert(krate.clone(), entry);
+    }
+
+    Ok(DbDump {
+        releases,
+        popularity,
+    })
+}
+
+// The kind column of the dependencies table: 0 normal, 1 build, 2 dev.
+const DEV_DEPENDENCY: u8 = 2;
+
+fn table(data_dir: &Path, name: &str) -> Result<PathBuf> {
+    let path = data_dir.join(name).with_extension("csv");
+    if !path.is_file() {
+        bail!("db dump is missing {}", path.display());
+    }
+    Ok(path)
+}
+
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub(crate) struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    pub(crate) fn quarter(self) -> Quarter {
+        Quarter {
+            year: self.year,
+            quarter: (self.month - 1) / 3 + 1,
+        }
+    }
+}
+
+// 2015-05-15, or a timestamp that starts with one, like the db dump's
+// 2015-05-15 12:34:56.789+00.
+impl FromStr for Date {
+    type Err = anyhow::Error;
+
+    fn from_str(string: &str) -> Result<Self> {
+        let parse = || {
+            let date = string.get(..10)?;
+            let mut parts = date.splitn(3, '-');
+            let year = parts.next()?.parse().ok()?;
+            let month = parts.next()?.parse().ok()?;
+            let day = parts.next()?.parse().ok()?;
+            ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some(Date {
+                year,
+                month,
+                day,
+            })
+        };
+        match parse() {
+            Some(date) => Ok(date),
+            None => bail!("invalid date: {:?}", string),
+        }
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{:04}-{:02}-{:02}",
+            self.year, self.month, self.day,
+        )
+    }
+}
+
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub(crate) struct Quarter {
+    pub year: u16,
+    pub quarter: u8,
+}
+
+impl Display for Quarter {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}Q{}", self.year, self.quarter)
+    }
+}
+
+impl Quarter {
+    pub(crate) fn next(self) -> Self {
+        if self.quarter == 4 {
+            Quarter {
+                year: self.year + 1,
+                quarter: 1,
+            }
+        } else {
+            Quarter {
+                year: self.year,
+                quarter: self.quarter + 1,
+            }
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use proc_macro2::LineColumn;
+
+// Rust code of one fenced code block in documentation, as rustdoc would
+// compile it.
+pub(crate) struct CodeBlock {
+    pub code: String,
+    // For each line of the code, its line number in the original file and the
+    // number of characters that precede it there, like `/// # `.
+    lines: Vec<(usize, usize)>,
+}
+
+impl CodeBlock {
+    // Location in the original file of a location in the code.
+    pub(crate) fn map(&self, pos: LineColumn) -> LineColumn {
+        match self.lines.get(pos.line.wrapping_sub(1)) {
+            Some(&(line, offset)) => LineColumn {
+                line,
+                column: offset + pos.column,
+            },
+            None => pos,
+        }
+    }
+}
+
+// Line of documentation: its line number in the original file, the number of
+// characters preceding it, and its text.
+type DocLine<'a> = (usize, usize, &'a str);
+
+// Code blocks in the `///` and `//!` comments of a Rust source file.
+pub(crate) fn comment_blocks(contents: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut doc = Vec::new();
+    let mut doc_style = "";
+    for (i, line) in contents.lines().enumerate() {
+        let trimmed = line.trim_start();
+        let style = if trimmed.starts_with("///") && !trimmed.starts_with("////") {
+            "///"
+        } else if trimmed.starts_with("//!") {
+            "//!"
+        } else {
+            ""
+        };
+        if style != doc_style {
+            blocks.extend(markdown_blocks(unindent(&doc)));
+            doc.clear();
+            doc_style = style;
+        }
+        if !style.is_empty() {
+            let text = &trimmed[style.len()..];
+            doc.push((i + 1, char_offset(line, text), text));
+        }
+    }
+    blocks.extend(markdown_blocks(unindent(&doc)));
+    blocks
+}
+
+// Code blocks in a markdown file, like a README included into the crate's
+// documentation by #![doc = include_str!("../README.md")].
+pub(crate) fn markdown_file_blocks(contents: &str) -> Vec<CodeBlock> {
+    let doc = contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, 0, line))
+        .collect();
+    markdown_blocks(doc)
+}
+
+// Rustdoc removes the indentation common to every line of a doc comment,
+// which is usually the one space after `///`.
+fn unindent<'a>(doc: &[DocLine<'a>]) -> Vec<DocLine<'a>> {
+    let indent = doc
+        .iter()
+        .filter(|(_line, _offset, text)| !text.trim().is_empty())
+        .map(|(_line, _offset, text)| text.len() - text.trim_start_matches(' ').len())
+        .min()
+        .unwrap_or(0);
+    doc.iter()
+        .map(|&(line, offset, text)| match text.get(..indent) {
+            Some(prefix) if prefix.trim().is_empty() => (line, offset + indent, &text[indent..]),
+            _ => {
+                let trimmed = text.trim_start();
+                (line, offset + char_offset(text, trimmed), trimmed)
+            }
+        })
+        .collect()
+}
+
+fn markdown_blocks(doc: Vec<DocLine>) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut lines = doc.into_iter();
+    while let Some((_line, _offset, text)) = lines.next() {
+        let Some((fence, indent, info)) = open_fence(text) else {
+            continue;
+        };
+        let mut block = CodeBlock {
+            code: String::new(),
+            lines: Vec::new(),
+        };
+        for (line, offset, text) in lines.by_ref() {
+            if is_close_fence(text, fence) {
+                break;
+            }
+            // Content of an indented fence is unindented by the same amount.
+            let skip = text.len() - text.trim_start_matches(' ').len();
+            let text = &text[skip.min(indent)..];
+            let code = hide(text);
+            block.code.push_str(code);
+            block.code.push('\n');
+            block
+                .lines
+                .push((line, offset + char_offset(text, code) + skip.min(indent)));
+        }
+        if is_rust(info) {
+            blocks.push(block);
+        }
+    }
+    blocks
+}
+
+// ```rust,no_run
+fn open_fence(text: &str) -> Option<(&str, usize, &str)> {
+    let trimmed = text.trim_start_matches(' ');
+    let indent = text.len() - trimmed.len();
+    if indent > 3 {
+        return None;
+    }
+    for ch in ['`', '~'] {
+        let len = trimmed.len() - trimmed.trim_start_matches(ch).len();
+        if len >= 3 {
+            let (fence, info) = trimmed.split_at(len);
+            if ch == '`' && info.contains('`') {
+                return None;
+            }
+            return Some((fence, indent, info.trim()));
+        }
+    }
+    None
+}
+
+fn is_close_fence(text: &str, fence: &str) -> bool {
+    let trimmed = text.trim();
+    let ch = fence.as_bytes()[0] as char;
+    trimmed.len() >= fence.len() && trimmed.chars().all(|c| c == ch)
+}
+
+// Blocks without a language are Rust, and so are blocks whose info string
+// only has rustdoc's own attributes, like ```ignore or ```edition2018.
+fn is_rust(info: &str) -> bool {
+    info.split(|ch: char| ch == ',' || ch.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .all(|token| {
+            matches!(
+                token,
+                "rust"
+                    | "ignore"
+                    | "should_panic"
+                    | "no_run"
+                    | "compile_fail"
+                    | "test_harness"
+                    | "allow_fail"
+                    | "standalone_crate"
+            ) || token.starts_with("edition")
+                || token.starts_with("ignore-")
+        })
+}
+
+// Lines that rustdoc hides from the rendered example, but compiles:
+//
+//     # #![allow(clippy::needless_doctest_main)]
+//     # fn main() {
+fn hide(text: &str) -> &str {
+    let trimmed = text.trim_start();
+    if trimmed == "#" {
+        ""
+    } else if let Some(rest) = trimmed.strip_prefix("# ") {
+        rest
+    } else if trimmed.starts_with("##") {
+        &trimmed[1..]
+    } else {
+        text
+    }
+}
+
+// Number of characters before a suffix of the given text.
+fn char_offset(text: &str, suffix: &str) -> usize {
+    text[..text.len() - suffix.len()].chars().count()
+}
@@ -0,0 +1,219 @@
+use crate::lints::{Lint, Namespace};
+use crate::parse::{Level, LintAttr};
+use crate::Span;
+use proc_macro2::LineColumn;
+use std::collections::BTreeMap as Map;
+use std::path::Path;
+
+// Files that pass lint levels to rustc or clippy-driver as command line flags.
+// Build scripts are not among them: Cargo rejects anything other than -l and
+// -L in cargo:rustc-flags.
+pub(crate) fn is_flags_file(relative_path: &Path) -> bool {
+    let Some(file_name) = relative_path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let parent = relative_path.parent().unwrap_or(Path::new(""));
+    let is_yaml = Path::new(file_name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml"));
+    // rustflags = ["-A", "clippy::x"]
+    (parent.ends_with(".cargo") && (file_name == "config.toml" || file_name == "config"))
+        // cargo clippy -- -A clippy::x
+        || (parent.ends_with(".github/workflows") && is_yaml)
+        || (parent.ends_with(".circleci") && is_yaml)
+        || matches!(
+            file_name,
+            ".gitlab-ci.yml"
+                | ".travis.yml"
+                | "appveyor.yml"
+                | ".appveyor.yml"
+                | "azure-pipelines.yml"
+                | ".cirrus.yml"
+        )
+}
+
+// One flag like `-A clippy::x`, with the location of the whole flag.
+pub(crate) struct Flag {
+    pub lint: LintAttr,
+    pub start: LineColumn,
+    pub end: LineColumn,
+}
+
+// Lint level flags, in any of the forms that rustc accepts:
+//
+//     -A clippy::x
+//     -Aclippy::x
+//     --allow clippy::x
+//     --allow=clippy::x
+//
+// Lints without a tool prefix are only taken if rustc knows them, since the
+// same short flags mean something else to other programs in a CI script.
+pub(crate) fn parse(contents: &str, rustc_lints: &Map<&str, &Lint>) -> Vec<Flag> {
+    let mut words = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        words.extend(split_words(i + 1, strip_comment(line)));
+    }
+
+    let mut flags = Vec::new();
+    let mut words = words.into_iter().peekable();
+    while let Some(word) = words.next() {
+        let Some((level, rest)) = split_level(word.text) else {
+            continue;
+        };
+        let (name, name_start) = if rest.is_empty() {
+            match words.peek() {
+                Some(next) => (next.text, next.start),
+                None => continue,
+            }
+        } else {
+            let offset = word.text.len() - rest.len();
+            let start = LineColumn {
+                line: word.start.line,
+                column: word.start.column + offset,
+            };
+            (rest, start)
+        };
+        let Some((namespace, lint_id)) = lint_name(name, rustc_lints) else {
+            continue;
+        };
+        if rest.is_empty() {
+            words.next();
+        }
+        let end = LineColumn {
+            line: name_start.line,
+            column: name_start.column + name.len(),
+        };
+        flags.push(Flag {
+            lint: LintAttr {
+                level,
+                namespace,
+                lint_id,
+                span: Span {
+                    start: name_start,
+                    end,
+                },
+                reason: None,
+            },
+            start: word.start,
+            end,
+        });
+    }
+    flags
+}
+
+// The line up to a `#` comment, which TOML and YAML files both use. A `#`
+// inside of a double-quoted string does not start one.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+struct Word<'a> {
+    start: LineColumn,
+    text: &'a str,
+}
+
+// Words of a line, separated by whitespace, quotes, and the punctuation of
+// TOML arrays and shell scripts. Flags are all ASCII, so other words are
+// dropped.
+fn split_words(line_number: usize, line: &str) -> Vec<Word<'_>> {
+    let is_separator = |ch: char| {
+        ch.is_whitespace() || matches!(ch, '"' | '\'' | '`' | ',' | '[' | ']' | '\\' | ';')
+    };
+    let mut words = Vec::new();
+    let mut start = None;
+    for (column, (i, ch)) in line.char_indices().enumerate() {
+        match (start, is_separator(ch)) {
+            (None, false) => start = Some((column, i)),
+            (Some((column_start, i_start)), true) => {
+                words.push(Word {
+                    start: LineColumn {
+                        line: line_number,
+                        column: column_start,
+                    },
+                    text: &line[i_start..i],
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((column_start, i_start)) = start {
+        words.push(Word {
+            start: LineColumn {
+                line: line_number,
+                column: column_start,
+            },
+            text: &line[i_start..],
+        });
+    }
+    // RUSTFLAGS=-Dwarnings
+    for word in &mut words {
+        if !word.text.starts_with('-') {
+            if let Some((key, value)) = word.text.split_once('=') {
+                word.start.column += key.chars().count() + 1;
+                word.text = value;
+            }
+        }
+    }
+    words.retain(|word| word.text.is_ascii());
+    words
+}
+
+// `-A` or `--allow=`, and whatever follows it in the same word.
+fn split_level(word: &str) -> Option<(Level, &str)> {
+    if let Some(long) = word.strip_prefix("--") {
+        for (name, level) in [
+            ("allow", Level::Allow),
+            ("expect", Level::Expect),
+            ("warn", Level::Warn),
+            ("force-warn", Level::Warn),
+            ("deny", Level::Deny),
+            ("forbid", Level::Forbid),
+        ] {
+            if let Some(rest) = long.strip_prefix(name) {
+                if rest.is_empty() {
+                    return Some((level, rest));
+                } else if let Some(rest) = rest.strip_prefix('=') {
+                    return Some((level, rest));
+                }
+            }
+        }
+        return None;
+    }
+    let rest = word.strip_prefix('-')?;
+    let level = match rest.as_bytes().first()? {
+        b'A' => Level::Allow,
+        b'W' => Level::Warn,
+        b'D' => Level::Deny,
+        b'F' => Level::Forbid,
+        _ => return None,
+    };
+    Some((level, &rest[1..]))
+}
+
+// clippy::x, rustdoc::x, or a rustc lint. Rustc also accepts dashes in place
+// of underscores on the command line.
+fn lint_name(name: &str, rustc_lints: &Map<&str, &Lint>) -> Option<(Namespace, String)> {
+    let is_lint_id = |id: &str| {
+        !id.is_empty()
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_')
+    };
+    let name = name.replace('-', "_");
+    if let Some((tool, lint_id)) = name.split_once("::") {
+        let namespace = Namespace::from_tool(tool)?;
+        return is_lint_id(lint_id).then(|| (namespace, lint_id.to_owned()));
+    }
+    // The bundled list of rustc lints includes its groups, like `warnings`.
+    let known = rustc_lints.contains_key(name.as_str());
+    (is_lint_id(&name) && known).then_some((Namespace::Rustc, name))
+}
@@ -0,0 +1,70 @@
+use std::path::{Component, Path};
+use syn::Item;
+
+// Phrases in the leading comments of a file that mark it as the output of a
+// code generator, like bindgen, prost, tonic, lalrpop, flatbuffers, or capnp.
+const MARKERS: &[&str] = &[
+    "@generated",
+    "automatically generated",
+    "auto-generated",
+    "autogenerated",
+    "bindings generated by",
+    "do not edit",
+];
+
+pub(crate) fn is_generated_file(relative_path: &Path, contents: &str) -> bool {
+    is_out_dir_path(relative_path) || has_generated_header(contents)
+}
+
+// Build script output that was checked in, like
+// target/debug/build/foo-0123456789abcdef/out/bindings.rs.
+fn is_out_dir_path(relative_path: &Path) -> bool {
+    let components = relative_path.components().collect::<Vec<_>>();
+    components.windows(3).any(|window| {
+        window[0] == Component::Normal("build".as_ref())
+            && window[2] == Component::Normal("out".as_ref())
+    })
+}
+
+fn has_generated_header(contents: &str) -> bool {
+    let header = contents
+        .lines()
+        .take(30)
+        .map(str::trim_start)
+        .take_while(|line| {
+            line.is_empty()
+                || line.starts_with("//")
+                || line.starts_with("/*")
+                || line.starts_with('*')
+        });
+    for line in header {
+        let line = line.to_ascii_lowercase();
+        if MARKERS.iter().any(|marker| line.contains(marker)) {
+            return true;
+        }
+    }
+    false
+}
+
+// A file or module that is only a wrapper around build script output:
+//
+//     include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+//
+// Imports alongside the include are allowed, but any other item means the
+// code around the include is handwritten.
+pub(crate) fn is_out_dir_wrapper(items: &[Item]) -> bool {
+    let mut includes_out_dir = false;
+    for item in items {
+        match item {
+            Item::Macro(item)
+                if item.mac.path.is_ident("include")
+                    && item.mac.tokens.to_string().contains("OUT_DIR") =>
+            {
+                includes_out_dir = true;
+            }
+            Item::Use(_) => {}
+            _ => return false,
+        }
+    }
+    includes_out_dir
+}
@@ -0,0 +1,63 @@
+use crate::dbdump::{Date, Quarter};
+use crate::name::Crate;
+use semver::Version;
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+// How many crates suppress a lint as of the end of one quarter, and how many
+// started or stopped suppressing it during the quarter.
+#[derive(PartialEq, Debug)]
+pub(crate) struct QuarterRow {
+    pub quarter: Quarter,
+    pub suppressing: usize,
+    pub appeared: usize,
+    pub disappeared: usize,
+}
+
+// Time series of one lint's suppressions. Each crate's versions are visited
+// in the order they were published; a suppression appears in the first
+// version that has it after one that did not, and disappears in the first
+// version without it after one that had it.
+pub(crate) fn quarterly(
+    suppressed: &Map<Crate, Set<Version>>,
+    published: &Map<Crate, Vec<(Date, Version)>>,
+) -> Vec<QuarterRow> {
+    let mut appeared = Map::new();
+    let mut disappeared = Map::new();
+    let mut last = None;
+    for (krate, versions) in published {
+        let mut state = false;
+        for (date, version) in versions {
+            let quarter = date.quarter();
+            last = last.max(Some(quarter));
+            let suppresses = suppressed
+                .get(krate)
+                .is_some_and(|versions| versions.contains(version));
+            if suppresses && !state {
+                *appeared.entry(quarter).or_insert(0) += 1;
+            } else if !suppresses && state {
+                *disappeared.entry(quarter).or_insert(0) += 1;
+            }
+            state = suppresses;
+        }
+    }
+
+    let mut rows = Vec::new();
+    let (Some(&first), Some(last)) = (appeared.keys().next(), last) else {
+        return rows;
+    };
+    let mut suppressing = 0;
+    let mut quarter = first;
+    while quarter <= last {
+        let appeared = appeared.get(&quarter).copied().unwrap_or(0);
+        let disappeared = disappeared.get(&quarter).copied().unwrap_or(0);
+        suppressing = suppressing + appeared - disappeared;
+        rows.push(QuarterRow {
+            quarter,
+            suppressing,
+            appeared,
+            disappeared,
+        });
+        quarter = quarter.next();
+    }
+    rows
+}
@@ -0,0 +1,151 @@
+use anyhow::{bail, Result};
+use semver::Version;
+use serde_derive::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// A local copy of the crates.io index: either a checkout of
+// https://github.com/rust-lang/crates.io-index, or the cache that Cargo keeps
+// of the sparse index in ~/.cargo/registry/index/index.crates.io-*/.cache.
+pub(crate) struct Index {
+    root: PathBuf,
+}
+
+// One line of an index file, describing one published version.
+#[derive(Deserialize, Clone)]
+pub(crate) struct IndexEntry {
+    pub name: String,
+    pub vers: Version,
+    #[serde(default)]
+    pub deps: Vec<Dependency>,
+    pub cksum: String,
+    #[serde(default)]
+    pub features: Map<String, Vec<String>>,
+    #[serde(default)]
+    pub features2: Map<String, Vec<String>>,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+#[derive(Deserialize, Clone)]
+pub(crate) struct Dependency {
+    pub name: String,
+    #[serde(default)]
+    pub optional: bool,
+}
+
+impl Index {
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let cache = path.join(".cache");
+        let root = if cache.is_dir() {
+            cache
+        } else {
+            path.to_owned()
+        };
+        if !root.is_dir() {
+            bail!("index not found at {}", path.display());
+        }
+        Ok(Index { root })
+    }
+
+    // Every published version of the crate, or an empty list if the index does
+    // not have the crate.
+    pub(crate) fn entries(&self, name: &str) -> Result<Vec<IndexEntry>> {
+        let path = self.root.join(relative_path(&name.to_ascii_lowercase()));
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        parse_entries(&contents)
+    }
+}
+
+// Entries of one index file, in either format.
+pub(crate) fn parse_entries(contents: &[u8]) -> Result<Vec<IndexEntry>> {
+    let mut entries = Vec::new();
+    for line in lines(contents) {
+        entries.push(serde_json::from_slice(line)?);
+    }
+    Ok(entries)
+}
+
+// Where files for the crate are, relative to the root of the index. The same
+// layout is used by mirrors of the .crate files.
+pub(crate) fn relative_path(name_lower: &str) -> PathBuf {
+    let mut path = PathBuf::new();
+    match name_lower.len() {
+        1 => path.push("1"),
+        2 => path.push("2"),
+        3 => path.extend(["3", &name_lower[..1]]),
+        _ => path.extend([&name_lower[0..2], &name_lower[2..4]]),
+    }
+    path.push(name_lower);
+    path
+}
+
+// JSON lines of an index file. A file of the git index has one per line. In
+// Cargo's cache of the sparse index, a header of one byte of cache version, 4
+// bytes of index version, and a NUL-terminated revision is followed by
+// NUL-terminated pairs of version number and JSON.
+fn lines(contents: &[u8]) -> Vec<&[u8]> {
+    if contents.first() == Some(&b'{') {
+        return contents
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .collect();
+    }
+    let Some(rest) = contents.get(5..) else {
+        return Vec::new();
+    };
+    let mut fields = rest.split(|b| *b == 0).skip(1);
+    let mut lines = Vec::new();
+    while let (Some(_version), Some(json)) = (fields.next(), fields.next()) {
+        if !json.is_empty() {
+            lines.push(json);
+        }
+    }
+    lines
+}
+
+impl IndexEntry {
+    // Names that can appear in cfg(feature = "..."): declared features, and
+    // optional dependencies that are not only referred to as "dep:name".
+    pub(crate) fn feature_names(&self) -> Set<String> {
+        let mut names = Set::new();
+        let mut dep_only = Set::new();
+        for (feature, enables) in self.features.iter().chain(&self.features2) {
+            names.insert(feature.clone());
+            for enabled in enables {
+                if let Some(dep) = enabled.strip_prefix("dep:") {
+                    dep_only.insert(dep);
+                }
+            }
+        }
+        for dep in &self.deps {
+            if dep.optional && !dep_only.contains(dep.name.as_str()) {
+                names.insert(dep.name.clone());
+            }
+        }
+        names
+    }
+
+    pub(crate) fn verify(&self, crate_file: &[u8]) -> Result<()> {
+        let mut checksum = String::new();
+        for byte in Sha256::digest(crate_file) {
+            let _ = write!(checksum, "{:02x}", byte);
+        }
+        if checksum != self.cksum {
+            bail!(
+                "checksum mismatch: expected {}, found {}",
+                self.cksum,
+                checksum
+            );
+        }
+        Ok(())
+    }
+}
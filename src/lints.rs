@@ -15,6 +15,8 @@ pub(crate) struct Lint {
 #[derive(Deserialize, PartialEq, Copy, Clone, Debug)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum LintGroup {
+    // clippy::all, which is never the group of an individual lint.
+    All,
     Cargo,
     Complexity,
     Correctness,
@@ -28,7 +30,7 @@ pub(crate) enum LintGroup {
     Unknown,
 }
 
-#[derive(Deserialize, PartialEq, Copy, Clone)]
+#[derive(Deserialize, PartialEq, Copy, Clone, Debug)]
 #[serde(rename_all = "lowercase")]
 pub(crate) enum LintLevel {
     Allow,
@@ -43,7 +45,145 @@ impl Display for LintGroup {
     }
 }
 
-pub(crate) fn download_lint_list() -> Result<Vec<Lint>> {
+impl LintGroup {
+    // Groups that can be named in a lint attribute, like
+    // #![allow(clippy::pedantic)].
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(LintGroup::All),
+            "cargo" => Some(LintGroup::Cargo),
+            "complexity" => Some(LintGroup::Complexity),
+            "correctness" => Some(LintGroup::Correctness),
+            "nursery" => Some(LintGroup::Nursery),
+            "pedantic" => Some(LintGroup::Pedantic),
+            "perf" => Some(LintGroup::Perf),
+            "restriction" => Some(LintGroup::Restriction),
+            "style" => Some(LintGroup::Style),
+            "suspicious" => Some(LintGroup::Suspicious),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn contains(self, group: LintGroup) -> bool {
+        match self {
+            LintGroup::All => matches!(
+                group,
+                LintGroup::Complexity
+                    | LintGroup::Correctness
+                    | LintGroup::Perf
+                    | LintGroup::Style
+                    | LintGroup::Suspicious
+            ),
+            _ => self == group,
+        }
+    }
+}
+
+// The tool whose lints are being counted.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub(crate) enum Namespace {
+    Clippy,
+    Rustc,
+    Rustdoc,
+}
+
+impl Namespace {
+    pub(crate) const ALL: [Self; 3] = [Namespace::Clippy, Namespace::Rustc, Namespace::Rustdoc];
+
+    // The first segment of a lint path like clippy::lint_id. Rustc lints are
+    // written without one.
+    pub(crate) fn from_tool(tool: &str) -> Option<Self> {
+        match tool {
+            "clippy" => Some(Namespace::Clippy),
+            "rustdoc" => Some(Namespace::Rustdoc),
+            _ => None,
+        }
+    }
+
+    // Source of the group and default level of every lint in the namespace.
+    pub(crate) fn lint_list(self) -> Result<Vec<Lint>> {
+        match self {
+            Namespace::Clippy => download_lint_list(),
+            Namespace::Rustc => Ok(bundled_lint_list(include_str!("rustc_lints.txt"))),
+            Namespace::Rustdoc => Ok(bundled_lint_list(include_str!("rustdoc_lints.txt"))),
+        }
+    }
+
+    // How the lint is written in a lint level attribute.
+    pub(crate) fn lint_path(self, lint_id: &str) -> String {
+        match self {
+            Namespace::Clippy | Namespace::Rustdoc => format!("{}::{}", self, lint_id),
+            Namespace::Rustc => lint_id.to_owned(),
+        }
+    }
+
+    // Path of a lint's page relative to the root of the site. Clippy's pages
+    // are at the root, where they have always been.
+    pub(crate) fn page(self, lint_id: &str) -> String {
+        match self {
+            Namespace::Clippy => format!("{}.html", lint_id),
+            Namespace::Rustc | Namespace::Rustdoc => format!("{}/{}.html", self, lint_id),
+        }
+    }
+
+    pub(crate) fn docs_url(self, lint_id: &str, level: LintLevel) -> String {
+        match self {
+            Namespace::Clippy => format!(
+                "https://rust-lang.github.io/rust-clippy/master/index.html#{}",
+                lint_id,
+            ),
+            Namespace::Rustc => {
+                let listing = "https://doc.rust-lang.org/rustc/lints/listing";
+                let anchor = lint_id.replace('_', "-");
+                match level {
+                    LintLevel::Allow => format!("{}/allowed-by-default.html#{}", listing, anchor),
+                    LintLevel::Warn => format!("{}/warn-by-default.html#{}", listing, anchor),
+                    LintLevel::Deny => format!("{}/deny-by-default.html#{}", listing, anchor),
+                    LintLevel::None => {
+                        "https://doc.rust-lang.org/rustc/lints/groups.html".to_owned()
+                    }
+                }
+            }
+            Namespace::Rustdoc => {
+                format!("https://doc.rust-lang.org/rustdoc/lints.html#{}", lint_id)
+            }
+        }
+    }
+}
+
+impl Display for Namespace {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&format!("{:?}", self).to_lowercase())
+    }
+}
+
+// One `lint_id level` per line, where level is allow, warn, deny, or group.
+fn bundled_lint_list(list: &str) -> Vec<Lint> {
+    let mut lints = Vec::new();
+    for line in list.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let Some((id, level)) = line.split_once(' ') else {
+            continue;
+        };
+        let level = match level {
+            "allow" => LintLevel::Allow,
+            "warn" => LintLevel::Warn,
+            "deny" => LintLevel::Deny,
+            _ => LintLevel::None,
+        };
+        lints.push(Lint {
+            id: id.to_owned(),
+            group: LintGroup::Unknown,
+            level,
+            former_ids: Set::new(),
+        });
+    }
+    lints
+}
+
+fn download_lint_list() -> Result<Vec<Lint>> {
     let req = reqwest::blocking::get("https://rust-lang.github.io/rust-clippy/master/lints.json")?;
     let lints: Vec<Lint> = req.json()?;
     Ok(lints)
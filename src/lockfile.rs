@@ -0,0 +1,45 @@
+use crate::name::Crate;
+use anyhow::Result;
+use semver::Version;
+use serde_derive::Deserialize;
+
+// Cargo.lock, or the output of `cargo metadata --format-version=1`. Both list
+// every package of the dependency graph under the same keys.
+#[derive(Deserialize)]
+struct Packages {
+    #[serde(default, alias = "package")]
+    packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    version: Version,
+    // Absent for workspace members and path dependencies.
+    source: Option<String>,
+}
+
+// Packages of the dependency graph that come from crates.io. Packages from
+// other registries, git, or the local filesystem have no .crate file among
+// the ones downloaded from crates.io.
+pub(crate) fn parse(contents: &str) -> Result<Vec<(Crate, Version)>> {
+    let packages: Packages = if contents.trim_start().starts_with('{') {
+        serde_json::from_str(contents)?
+    } else {
+        toml::from_str(contents)?
+    };
+    let mut crates = Vec::new();
+    for package in packages.packages {
+        if package.source.as_deref().is_some_and(is_crates_io) {
+            crates.push((Crate::new(package.name), package.version));
+        }
+    }
+    crates.sort();
+    crates.dedup();
+    Ok(crates)
+}
+
+fn is_crates_io(source: &str) -> bool {
+    source == "registry+https://github.com/rust-lang/crates.io-index"
+        || source == "sparse+https://index.crates.io/"
+}
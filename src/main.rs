@@ -8,64 +8,448 @@
     clippy::match_same_arms,
     clippy::needless_lifetimes,
     clippy::similar_names,
+    clippy::struct_excessive_bools,
     clippy::too_many_lines,
     clippy::uninlined_format_args,
     clippy::unwrap_or_default
 )]
 
+mod config;
+mod dbdump;
+mod doc;
+mod flags;
+mod generated;
+mod history;
+mod index;
 mod lints;
+mod lockfile;
+mod manifest;
+mod modules;
 mod name;
 mod parse;
 mod render;
+mod select;
+mod target;
+mod weight;
 
 #[cfg(test)]
 mod tests;
 
-use crate::lints::{Lint, LintGroup, LintLevel};
+use crate::dbdump::{Date, Popularity};
+use crate::doc::CodeBlock;
+use crate::flags::Flag;
+use crate::index::{Index, IndexEntry};
+use crate::lints::{Lint, LintGroup, LintLevel, Namespace};
+use crate::modules::{Governed, ModuleTree, Outline, Source};
 use crate::name::Crate;
+use crate::parse::{Level, LintAttr, Shim};
 use crate::render::render;
-use anyhow::Result;
+use crate::select::{Policy, VersionInfo};
+use crate::target::{TargetKind, Targets};
+use crate::weight::SortBy;
+use anyhow::{bail, Result};
 use clap::Parser;
 use flate2::read::GzDecoder;
 use git2::{BranchType, FileMode, Repository, Signature};
 use parking_lot::Mutex;
-use proc_macro2::LineColumn;
+use proc_macro2::{Delimiter, LineColumn, TokenStream, TokenTree};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rayon::ThreadPoolBuilder;
 use semver::Version;
 use std::cmp::Reverse;
 use std::collections::btree_map::{BTreeMap as Map, Entry};
+use std::collections::BTreeSet as Set;
 use std::ffi::OsStr;
-use std::fs::File;
-use std::io::{self, BufReader, Read, Write};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
 use std::iter;
+use std::mem;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use syn::visit::Visit;
-use syn::{AttrStyle, Attribute};
+use syn::parse::ParseStream;
+use syn::visit::{self, Visit};
+use syn::{AttrStyle, Attribute, Macro, Meta, Stmt};
 use tar::Archive;
 use walkdir::WalkDir;
 
 struct AttrVisitor<'a> {
     source_file: &'a SourceFile,
     contents: Arc<String>,
-    findings: &'a Mutex<Findings>,
-    lints: &'a Map<&'a str, &'a Lint>,
+    collected: &'a Collected,
+    lints: &'a Map<Namespace, Lints<'a>>,
+    proc_macro: bool,
+    // Features of the crate according to the index, if it has the crate.
+    features: Option<&'a Set<String>>,
+    // Which source files of the package are compiled.
+    modules: &'a ModuleTree,
+    // Inline modules that the visitor is currently inside of.
+    inline_mods: Vec<String>,
+    cx: Context,
+    // Set while visiting a code block from the documentation, whose locations
+    // need to be mapped back to the file containing the documentation.
+    doc_example: Option<CodeBlock>,
+    // Markdown files included as documentation by the visited file.
+    doc_includes: Vec<PathBuf>,
+}
+
+// What every file of one crate is visited with.
+struct CrateScan<'a> {
+    collected: &'a Collected,
+    lints: &'a Map<Namespace, Lints<'a>>,
+    proc_macro: bool,
+    features: Option<&'a Set<String>>,
+    modules: &'a ModuleTree,
+}
+
+impl<'a> CrateScan<'a> {
+    fn visitor(
+        &self,
+        source_file: &'a SourceFile,
+        contents: String,
+        cx: Context,
+    ) -> AttrVisitor<'a> {
+        AttrVisitor {
+            source_file,
+            contents: Arc::new(contents),
+            collected: self.collected,
+            lints: self.lints,
+            proc_macro: self.proc_macro,
+            features: self.features,
+            modules: self.modules,
+            inline_mods: Vec::new(),
+            cx,
+            doc_example: None,
+            doc_includes: Vec::new(),
+        }
+    }
+}
+
+// What is known about the attribute currently being visited, from the nodes
+// that it is nested in.
+#[derive(Copy, Clone, Default)]
+struct Context {
+    // The syntax that the attribute applies to.
+    target: Target,
+    // Whether the attribute is within an item that is only compiled for tests.
+    cfg_test: bool,
+    // Whether the attribute is in the output of a code generator, or applies
+    // to a module that includes some.
+    generated: bool,
+    // How much code the attribute applies to.
+    governed: Governed,
+    // Whether unknown_lints or renamed_and_removed_lints is allowed alongside
+    // the attribute, on the same item or an enclosing one.
+    shim: Option<Shim>,
 }
 
 type Findings = Map<String, Map<SourceFile, Locations>>;
 
+// Every lint in a namespace by its current and former ids.
+type Lints<'a> = Map<&'a str, &'a Lint>;
+
+// Crates that opt in to each lint or lint group using warn, deny, or forbid.
+type Enabled = Map<String, Set<Crate>>;
+
+// Value set by each crate for each clippy.toml option.
+type Configs = Map<String, Map<Crate, String>>;
+
+// Crate versions that suppress each lint, for --history.
+type History = Map<String, Map<Crate, Set<Version>>>;
+
+// Everything gathered from the crates, which are parsed in parallel.
+#[derive(Default)]
+struct Collected {
+    findings: Mutex<Map<Namespace, Findings>>,
+    // In place of findings, when scanning every version of every crate.
+    history: Mutex<Map<Namespace, History>>,
+    enabled: Mutex<Map<Namespace, Enabled>>,
+    configs: Mutex<Configs>,
+    file_counts: Mutex<FileCounts>,
+}
+
+// How each source file was scanned.
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum FileStatus {
+    Parsed,
+    // Syn could not parse the file, but it could be tokenized.
+    Fallback,
+    // Not UTF-8, or not even tokenizable.
+    Unreadable,
+}
+
+#[derive(Default)]
+struct FileCounts {
+    parsed: usize,
+    fallback: usize,
+    unreadable: usize,
+    generated: usize,
+    // Not reachable by `mod` from the root of any target.
+    dead: usize,
+}
+
+impl Collected {
+    // Keeps only which lints the given crate version suppresses, out of what
+    // was gathered from it.
+    fn add_history(&self, scanned: Collected, opt: &Opt, clippy_lints: &[Lint]) {
+        let mut findings = scanned.findings.into_inner();
+        filter_findings(&mut findings, opt);
+        if opt.expand_groups {
+            expand_groups(&mut findings, clippy_lints);
+        }
+        let mut history = self.history.lock();
+        for (namespace, findings) in findings {
+            let history = history.entry(namespace).or_insert_with(Map::new);
+            for (lint_id, findings) in findings {
+                let crates = history.entry(lint_id).or_insert_with(Map::new);
+                for source_file in findings.into_keys() {
+                    let versions = crates.entry(source_file.krate).or_insert_with(Set::new);
+                    versions.insert(source_file.version);
+                }
+            }
+        }
+        let file_counts = scanned.file_counts.into_inner();
+        self.file_counts.lock().extend(&file_counts);
+    }
+}
+
+impl FileCounts {
+    fn add(&mut self, status: FileStatus) {
+        match status {
+            FileStatus::Parsed => self.parsed += 1,
+            FileStatus::Fallback => self.fallback += 1,
+            FileStatus::Unreadable => self.unreadable += 1,
+        }
+    }
+
+    fn extend(&mut self, other: &FileCounts) {
+        self.parsed += other.parsed;
+        self.fallback += other.fallback;
+        self.unreadable += other.unreadable;
+        self.generated += other.generated;
+        self.dead += other.dead;
+    }
+}
+
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
 struct SourceFile {
     krate: Crate,
     version: Version,
     relative_path: PathBuf,
+    target_kind: TargetKind,
 }
 
+#[derive(Clone)]
 struct Locations {
     contents: Arc<String>,
     global: Vec<Span>,
     local: Vec<Span>,
+    manifest: Vec<Span>,
+    in_macro: Vec<Span>,
+    proc_macro: Vec<Span>,
+    // Code blocks in doc comments, and in markdown included as documentation.
+    doc_example: Vec<Span>,
+    // Flags like `-A clippy::x` in .cargo/config.toml or CI config.
+    command_line: Vec<Span>,
+    expect: Vec<Span>,
+    // Reason given in the attribute, or a comment next to it.
+    justifications: Map<Span, String>,
+    targets: Map<Span, Target>,
+    // Suppressions within #[cfg(test)] code.
+    cfg_test: Set<Span>,
+    // Suppressions within generated code.
+    generated: Set<Span>,
+    governed: Map<Span, Governed>,
+    // Suppressions accompanied by an allow of unknown_lints or
+    // renamed_and_removed_lints.
+    shims: Map<Span, Shim>,
+    // Suppressions that refer to a lint by the name it had before a rename.
+    former_ids: Map<Span, String>,
+}
+
+#[derive(Copy, Clone)]
+enum Scope {
+    Global,
+    Local,
+    Manifest,
+    Macro,
+    ProcMacro,
+    DocExample,
+    CommandLine,
+}
+
+// The syntactic item that a lint level attribute applies to.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+enum Target {
+    CrateRoot,
+    Module,
+    Impl,
+    Trait,
+    Fn,
+    // Struct, enum, or union.
+    Type,
+    // Struct field or enum variant.
+    Field,
+    Statement,
+    Expression,
+    // Anything else, like a const, a use, a function argument, or tokens
+    // inside a macro.
+    #[default]
+    Other,
+}
+
+impl Target {
+    const ALL: [Self; 10] = [
+        Target::CrateRoot,
+        Target::Module,
+        Target::Impl,
+        Target::Trait,
+        Target::Fn,
+        Target::Type,
+        Target::Field,
+        Target::Statement,
+        Target::Expression,
+        Target::Other,
+    ];
+
+    // Used in the report's column header.
+    fn title(self) -> &'static str {
+        match self {
+            Target::CrateRoot => "crate root",
+            Target::Module => "module",
+            Target::Impl => "impl",
+            Target::Trait => "trait",
+            Target::Fn => "fn",
+            Target::Type => "struct/enum",
+            Target::Field => "field",
+            Target::Statement => "statement",
+            Target::Expression => "expression",
+            Target::Other => "other",
+        }
+    }
+
+    // Used in the HTML page's filters.
+    fn name(self) -> &'static str {
+        match self {
+            Target::CrateRoot => "crate",
+            Target::Module => "mod",
+            Target::Impl => "impl",
+            Target::Trait => "trait",
+            Target::Fn => "fn",
+            Target::Type => "type",
+            Target::Field => "field",
+            Target::Statement => "stmt",
+            Target::Expression => "expr",
+            Target::Other => "other",
+        }
+    }
+}
+
+impl Locations {
+    fn new(contents: &Arc<String>) -> Self {
+        Locations {
+            contents: Arc::clone(contents),
+            global: Vec::new(),
+            local: Vec::new(),
+            manifest: Vec::new(),
+            in_macro: Vec::new(),
+            proc_macro: Vec::new(),
+            doc_example: Vec::new(),
+            command_line: Vec::new(),
+            expect: Vec::new(),
+            justifications: Map::new(),
+            targets: Map::new(),
+            cfg_test: Set::new(),
+            generated: Set::new(),
+            governed: Map::new(),
+            shims: Map::new(),
+            former_ids: Map::new(),
+        }
+    }
+
+    fn extend(&mut self, other: &Locations) {
+        self.global.extend(&other.global);
+        self.local.extend(&other.local);
+        self.manifest.extend(&other.manifest);
+        self.in_macro.extend(&other.in_macro);
+        self.proc_macro.extend(&other.proc_macro);
+        self.doc_example.extend(&other.doc_example);
+        self.command_line.extend(&other.command_line);
+        self.expect.extend(&other.expect);
+        self.justifications.extend(other.justifications.clone());
+        self.targets.extend(other.targets.clone());
+        self.cfg_test.extend(&other.cfg_test);
+        self.generated.extend(&other.generated);
+        self.governed.extend(&other.governed);
+        self.shims.extend(&other.shims);
+        self.former_ids.extend(other.former_ids.clone());
+    }
+
+    fn spans(&self) -> impl Iterator<Item = &Span> {
+        self.global
+            .iter()
+            .chain(&self.local)
+            .chain(&self.manifest)
+            .chain(&self.in_macro)
+            .chain(&self.proc_macro)
+            .chain(&self.doc_example)
+            .chain(&self.command_line)
+            .chain(&self.expect)
+    }
+
+    fn len(&self) -> usize {
+        self.global.len()
+            + self.local.len()
+            + self.manifest.len()
+            + self.in_macro.len()
+            + self.proc_macro.len()
+            + self.doc_example.len()
+            + self.command_line.len()
+            + self.expect.len()
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(&Span) -> bool) {
+        self.global.retain(&mut f);
+        self.local.retain(&mut f);
+        self.manifest.retain(&mut f);
+        self.in_macro.retain(&mut f);
+        self.proc_macro.retain(&mut f);
+        self.doc_example.retain(&mut f);
+        self.command_line.retain(&mut f);
+        self.expect.retain(&mut f);
+    }
+
+    // Keeps spans of each kind in turn, so that many of one kind do not push
+    // out all of another.
+    fn truncate(&mut self, len: usize) {
+        let buckets = [
+            &mut self.global,
+            &mut self.local,
+            &mut self.manifest,
+            &mut self.in_macro,
+            &mut self.proc_macro,
+            &mut self.doc_example,
+            &mut self.command_line,
+            &mut self.expect,
+        ];
+        let mut keep = [0; 8];
+        let mut remaining = len;
+        while remaining > 0 {
+            let mut kept = false;
+            for (spans, keep) in buckets.iter().zip(&mut keep) {
+                if remaining > 0 && *keep < spans.len() {
+                    *keep += 1;
+                    remaining -= 1;
+                    kept = true;
+                }
+            }
+            if !kept {
+                break;
+            }
+        }
+        for (spans, keep) in buckets.into_iter().zip(keep) {
+            spans.truncate(keep);
+        }
+    }
 }
 
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
@@ -74,47 +458,544 @@ struct Span {
     end: LineColumn,
 }
 
-// Find all lint level attributes and count how many times each Clippy lint is
-// allowed.
+impl SourceFile {
+    // Whether the file's inner attributes apply to a whole crate, going by
+    // Cargo's conventional target locations.
+    fn is_crate_root(&self) -> bool {
+        target::is_conventional_root(&self.relative_path)
+    }
+
+    // Cargo.toml, .cargo/config, and CI configuration, as opposed to Rust and
+    // Markdown files, whose scanned code is Rust.
+    fn has_hash_comments(&self) -> bool {
+        let path = &self.relative_path;
+        path.ends_with(".cargo/config")
+            || path.extension().is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("toml")
+                    || ext.eq_ignore_ascii_case("yml")
+                    || ext.eq_ignore_ascii_case("yaml")
+            })
+    }
+}
+
+// Visits a node with the given target for the attributes on it and, unless a
+// more specific one applies, those nested inside of it. Nodes that are part of
+// an item, rather than an item, govern no items of their own.
+macro_rules! visit_with_target {
+    ($($method:ident($node:ident) => $target:expr, $governed:expr;)*) => {
+        $(
+            fn $method(&mut self, node: &'ast syn::$node) {
+                self.within($target, $governed, |visitor| visit::$method(visitor, node));
+            }
+        )*
+    };
+}
+
+// Visits a node that attributes can be on, but that is part of the same kind of
+// syntax as the node it is nested in, like the arms of a match expression.
+// Whatever the attributes on it imply applies only within the node.
+macro_rules! visit_scoped {
+    ($($method:ident($node:ident);)*) => {
+        $(
+            fn $method(&mut self, node: &'ast syn::$node) {
+                let outer = self.cx;
+                visit::$method(self, node);
+                self.cx = outer;
+            }
+        )*
+    };
+}
+
+// Find all lint level attributes and count how many times each lint is allowed,
+// and how many crates opt in to it.
 impl<'ast, 'a> Visit<'ast> for AttrVisitor<'a> {
     fn visit_attribute(&mut self, attr: &'ast Attribute) {
-        let attr_path = attr.path();
-        let parser = if attr_path.is_ident("allow") {
-            parse::allow
-        } else if attr_path.is_ident("cfg_attr") {
-            parse::cfg_attr
+        let scope = match attr.style {
+            AttrStyle::Outer => Scope::Local,
+            AttrStyle::Inner(_) => Scope::Global,
+        };
+        // Lint attributes that come after #[cfg(test)] on the same item, and
+        // everything nested in the item, are only compiled for tests.
+        if parse::is_cfg_test(&attr.meta) {
+            self.cx.cfg_test = true;
+        }
+        if let Some(path) = parse::doc_include(&attr.meta) {
+            let dir = self.source_file.relative_path.parent();
+            let path = modules::normalize(&dir.unwrap_or(Path::new("")).join(path));
+            self.doc_includes.push(path);
+        }
+        let start = attr.pound_token.span.start();
+        let end = attr.bracket_token.span.close().end();
+        let lints = self.lint_attr(&attr.meta);
+        // Attributes that come after the shim on the same item, and
+        // everything nested in the item, are covered by it.
+        if let Some(shim) = Shim::find(&lints) {
+            self.cx.shim = Some(shim);
+        }
+        for lint in lints {
+            self.record(lint, scope, self.cx, start, end);
+        }
+    }
+
+    fn visit_file(&mut self, node: &'ast syn::File) {
+        let path = &self.source_file.relative_path;
+        let descendants = self.modules.descendants(path);
+        self.cx = Context {
+            target: if self.source_file.is_crate_root() || self.modules.is_root(path) {
+                Target::CrateRoot
+            } else {
+                Target::Module
+            },
+            generated: self.cx.generated || generated::is_out_dir_wrapper(&node.items),
+            governed: Governed {
+                files: 1 + descendants.files,
+                items: modules::count_items(|counter| counter.visit_file(node)) + descendants.items,
+            },
+            ..self.cx
+        };
+        visit::visit_file(self, node);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        // Files declared by this module, like by `mod m;`.
+        let declared =
+            self.modules
+                .declared_by(&self.source_file.relative_path, &self.inline_mods, node);
+        let governed = Governed {
+            files: declared.files,
+            items: self.cx.governed.items + declared.items,
+        };
+        let outer_generated = self.cx.generated;
+        if let Some((_brace, items)) = &node.content {
+            if generated::is_out_dir_wrapper(items) {
+                self.cx.generated = true;
+            }
+        }
+        self.inline_mods.push(node.ident.to_string());
+        self.within(Target::Module, Some(governed), |visitor| {
+            visit::visit_item_mod(visitor, node);
+        });
+        self.inline_mods.pop();
+        self.cx.generated = outer_generated;
+    }
+
+    fn visit_stmt(&mut self, node: &'ast Stmt) {
+        self.within(Target::Statement, Some(Governed::default()), |visitor| {
+            match node {
+                // Attributes on an expression statement apply to the
+                // statement, not to the expression.
+                Stmt::Expr(expr, _semi) => visit::visit_expr(visitor, expr),
+                _ => visit::visit_stmt(visitor, node),
+            }
+        });
+    }
+
+    fn visit_item(&mut self, node: &'ast syn::Item) {
+        let governed = Governed {
+            files: 0,
+            items: modules::count_items(|counter| counter.visit_item(node)),
+        };
+        self.within(Target::Other, Some(governed), |visitor| {
+            visit::visit_item(visitor, node);
+        });
+    }
+
+    fn visit_impl_item(&mut self, node: &'ast syn::ImplItem) {
+        let governed = Governed {
+            files: 0,
+            items: modules::count_items(|counter| counter.visit_impl_item(node)),
+        };
+        self.within(Target::Other, Some(governed), |visitor| {
+            visit::visit_impl_item(visitor, node);
+        });
+    }
+
+    fn visit_trait_item(&mut self, node: &'ast syn::TraitItem) {
+        let governed = Governed {
+            files: 0,
+            items: modules::count_items(|counter| counter.visit_trait_item(node)),
+        };
+        self.within(Target::Other, Some(governed), |visitor| {
+            visit::visit_trait_item(visitor, node);
+        });
+    }
+
+    visit_with_target! {
+        visit_item_impl(ItemImpl) => Target::Impl, None;
+        visit_item_trait(ItemTrait) => Target::Trait, None;
+        visit_item_fn(ItemFn) => Target::Fn, None;
+        visit_item_struct(ItemStruct) => Target::Type, None;
+        visit_item_enum(ItemEnum) => Target::Type, None;
+        visit_item_union(ItemUnion) => Target::Type, None;
+        visit_impl_item_fn(ImplItemFn) => Target::Fn, None;
+        visit_trait_item_fn(TraitItemFn) => Target::Fn, None;
+        visit_foreign_item(ForeignItem) => Target::Other, Some(Governed { files: 0, items: 1 });
+        visit_fn_arg(FnArg) => Target::Other, Some(Governed::default());
+        visit_generic_param(GenericParam) => Target::Other, Some(Governed::default());
+        visit_field(Field) => Target::Field, Some(Governed::default());
+        visit_variant(Variant) => Target::Field, Some(Governed::default());
+        visit_expr(Expr) => Target::Expression, Some(Governed::default());
+    }
+
+    visit_scoped! {
+        visit_arm(Arm);
+        visit_field_value(FieldValue);
+        visit_field_pat(FieldPat);
+        visit_pat(Pat);
+    }
+
+    // Syn does not look inside of macro invocations and macro_rules bodies, so
+    // scan their tokens for anything that looks like an attribute. In a
+    // proc-macro crate, attributes inside quote! are emitted into the code of
+    // the macro's users.
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let is_quote =
+            mac.path.segments.last().is_some_and(|segment| {
+                segment.ident == "quote" || segment.ident == "quote_spanned"
+            });
+        let scope = if self.proc_macro && is_quote {
+            Scope::ProcMacro
         } else {
-            return;
+            Scope::Macro
         };
-        let Ok(lints) = attr.parse_args_with(parser) else {
-            return;
+        self.scan_tokens(mac.tokens.clone(), Some(scope), false);
+        visit::visit_macro(self, mac);
+    }
+}
+
+impl<'a> AttrVisitor<'a> {
+    fn scan_file(&mut self, source: &Source) -> FileStatus {
+        let status = match source {
+            Source::Syntax(syntax) => {
+                self.visit_file(syntax);
+                FileStatus::Parsed
+            }
+            Source::Tokens(tokens) => {
+                // Attributes can still be found among the tokens of a file
+                // that syn could not parse.
+                let path = &self.source_file.relative_path;
+                let descendants = self.modules.descendants(path);
+                self.cx.target = if self.source_file.is_crate_root() || self.modules.is_root(path) {
+                    Target::CrateRoot
+                } else {
+                    Target::Module
+                };
+                self.cx.governed = Governed {
+                    files: 1 + descendants.files,
+                    items: descendants.items,
+                };
+                let scope = None;
+                let top_level = true;
+                self.scan_tokens(tokens.clone(), scope, top_level);
+                FileStatus::Fallback
+            }
+            Source::Unreadable => FileStatus::Unreadable,
         };
-        if lints.is_empty() {
-            return;
+        let contents = Arc::clone(&self.contents);
+        for block in doc::comment_blocks(&contents) {
+            self.scan_doc_example(block);
         }
-        let mut findings = self.findings.lock();
-        for (mut lint_id, span) in lints {
-            lint_id = match self.lints.get(lint_id.as_str()) {
-                Some(renamed_lint) => renamed_lint.id.clone(),
-                None => lint_id,
+        status
+    }
+
+    fn scan_markdown(&mut self) {
+        let contents = Arc::clone(&self.contents);
+        for block in doc::markdown_file_blocks(&contents) {
+            self.scan_doc_example(block);
+        }
+    }
+
+    // Rustdoc compiles each code block of the documentation as its own crate.
+    fn scan_doc_example(&mut self, block: CodeBlock) {
+        let source = Source::parse(&block.code);
+        let outer = self.cx;
+        self.cx.target = Target::CrateRoot;
+        self.doc_example = Some(block);
+        match &source {
+            Source::Syntax(syntax) => {
+                self.cx.governed = Governed {
+                    files: 0,
+                    items: modules::count_items(|counter| counter.visit_file(syntax)),
+                };
+                visit::visit_file(self, syntax);
+            }
+            Source::Tokens(tokens) => {
+                self.cx.governed = Governed::default();
+                let scope = None;
+                let top_level = true;
+                self.scan_tokens(tokens.clone(), scope, top_level);
+            }
+            Source::Unreadable => {}
+        }
+        self.doc_example = None;
+        self.cx = outer;
+    }
+
+    // Visits a node that is of the given kind, and which governs the given
+    // amount of code if known more precisely than by what it is nested in.
+    fn within(&mut self, target: Target, governed: Option<Governed>, f: impl FnOnce(&mut Self)) {
+        let outer = self.cx;
+        self.cx.target = target;
+        if let Some(governed) = governed {
+            self.cx.governed = governed;
+        }
+        f(self);
+        self.cx = outer;
+    }
+
+    fn lint_attr(&self, meta: &Meta) -> Vec<LintAttr> {
+        let Meta::List(list) = meta else {
+            return Vec::new();
+        };
+        if let Some(level) = list.path.get_ident().and_then(Level::from_ident) {
+            let parser = |input: ParseStream| parse::allow(input, level);
+            list.parse_args_with(parser).unwrap_or_default()
+        } else if list.path.is_ident("cfg_attr") {
+            let clippy_lints = &self.lints[&Namespace::Clippy];
+            let parser = |input: ParseStream| parse::cfg_attr(input, clippy_lints, self.features);
+            list.parse_args_with(parser).unwrap_or_default()
+        } else {
+            Vec::new()
+        }
+    }
+
+    // Within a macro, every attribute gets the macro's scope. Scope is None
+    // when scanning a whole file that syn could not parse, in which case the
+    // scope comes from the attribute's style, and macro invocations are
+    // recognized by their `!`.
+    // Top level means directly in the file, not nested in any group.
+    fn scan_tokens(&self, tokens: TokenStream, scope: Option<Scope>, top_level: bool) {
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                TokenTree::Punct(pound) if pound.as_char() == '#' => {
+                    let mut attr_scope = Scope::Local;
+                    if let Some(TokenTree::Punct(bang)) = tokens.peek() {
+                        if bang.as_char() == '!' {
+                            tokens.next();
+                            attr_scope = Scope::Global;
+                        }
+                    }
+                    let Some(TokenTree::Group(group)) = tokens.peek() else {
+                        continue;
+                    };
+                    if group.delimiter() != Delimiter::Bracket {
+                        continue;
+                    }
+                    if let Ok(meta) = syn::parse2::<Meta>(group.stream()) {
+                        let start = pound.span().start();
+                        let end = group.span_close().end();
+                        // Inner attributes at the top level of a file that syn
+                        // could not parse are the only ones whose target is
+                        // known.
+                        let mut cx = match (scope, attr_scope) {
+                            (None, Scope::Global) if top_level => self.cx,
+                            _ => Context {
+                                target: Target::Other,
+                                governed: Governed::default(),
+                                ..self.cx
+                            },
+                        };
+                        let lints = self.lint_attr(&meta);
+                        if let Some(shim) = Shim::find(&lints) {
+                            cx.shim = Some(shim);
+                        }
+                        for lint in lints {
+                            self.record(lint, scope.unwrap_or(attr_scope), cx, start, end);
+                        }
+                    }
+                    tokens.next();
+                }
+                // `path!(...)` or `macro_rules! name {...}`, but not a unary
+                // `!` after a keyword.
+                TokenTree::Ident(ident)
+                    if scope.is_none()
+                        && !matches!(
+                            ident.to_string().as_str(),
+                            "break" | "if" | "in" | "match" | "return" | "while" | "yield",
+                        ) =>
+                {
+                    let Some(TokenTree::Punct(bang)) = tokens.peek() else {
+                        continue;
+                    };
+                    if bang.as_char() != '!' {
+                        continue;
+                    }
+                    tokens.next();
+                    if let Some(TokenTree::Ident(_name)) = tokens.peek() {
+                        tokens.next();
+                    }
+                    let Some(TokenTree::Group(group)) = tokens.peek() else {
+                        continue;
+                    };
+                    let is_quote = ident == "quote" || ident == "quote_spanned";
+                    let macro_scope = if self.proc_macro && is_quote {
+                        Scope::ProcMacro
+                    } else {
+                        Scope::Macro
+                    };
+                    self.scan_tokens(group.stream(), Some(macro_scope), false);
+                    tokens.next();
+                }
+                TokenTree::Group(group) => self.scan_tokens(group.stream(), scope, false),
+                _ => {}
+            }
+        }
+    }
+
+    // The start and end are the location of the whole attribute, next to which
+    // there may be a comment explaining the suppression.
+    fn record(
+        &self,
+        lint: LintAttr,
+        mut scope: Scope,
+        cx: Context,
+        mut start: LineColumn,
+        mut end: LineColumn,
+    ) {
+        let LintAttr {
+            level,
+            namespace,
+            mut lint_id,
+            mut span,
+            reason,
+        } = lint;
+        if let Some(block) = &self.doc_example {
+            // Lints enabled by an example do not apply to the crate itself.
+            if level.is_enabled() {
+                return;
+            }
+            scope = Scope::DocExample;
+            span = Span {
+                start: block.map(span.start),
+                end: block.map(span.end),
             };
-            let locations = findings
-                .entry(lint_id)
-                .or_insert_with(Map::new)
-                .entry(self.source_file.clone())
-                .or_insert_with(|| Locations {
-                    contents: Arc::clone(&self.contents),
-                    global: Vec::new(),
-                    local: Vec::new(),
-                });
-            match attr.style {
-                AttrStyle::Outer => locations.local.push(span),
-                AttrStyle::Inner(_) => locations.global.push(span),
+            start = block.map(start);
+            end = block.map(end);
+        }
+        let mut former_id = None;
+        if let Some(renamed_lint) = self.lints[&namespace].get(lint_id.as_str()) {
+            if renamed_lint.id != lint_id {
+                former_id = Some(mem::replace(&mut lint_id, renamed_lint.id.clone()));
             }
         }
+        if level.is_enabled() {
+            let mut enabled = self.collected.enabled.lock();
+            let crates = enabled
+                .entry(namespace)
+                .or_insert_with(Map::new)
+                .entry(lint_id)
+                .or_insert_with(Set::new);
+            crates.insert(self.source_file.krate.clone());
+            return;
+        }
+        let mut findings = self.collected.findings.lock();
+        let locations = findings
+            .entry(namespace)
+            .or_insert_with(Map::new)
+            .entry(lint_id)
+            .or_insert_with(Map::new)
+            .entry(self.source_file.clone())
+            .or_insert_with(|| Locations::new(&self.contents));
+        match (level, scope) {
+            (_, Scope::DocExample) => locations.doc_example.push(span),
+            (Level::Expect, _) => locations.expect.push(span),
+            (_, Scope::CommandLine) => locations.command_line.push(span),
+            (_, Scope::Global) => locations.global.push(span),
+            (_, Scope::Local) => locations.local.push(span),
+            (_, Scope::Manifest) => locations.manifest.push(span),
+            (_, Scope::Macro) => locations.in_macro.push(span),
+            (_, Scope::ProcMacro) => locations.proc_macro.push(span),
+        }
+        locations.targets.insert(span, cx.target);
+        locations.governed.insert(span, cx.governed);
+        if let Some(shim) = cx.shim {
+            locations.shims.insert(span, shim);
+        }
+        if let Some(former_id) = former_id {
+            locations.former_ids.insert(span, former_id);
+        }
+        if cx.cfg_test {
+            locations.cfg_test.insert(span);
+        }
+        if cx.generated {
+            locations.generated.insert(span);
+        }
+        if let Some(justification) = reason.or_else(|| self.adjacent_comment(start, end)) {
+            locations.justifications.insert(span, justification);
+        }
+    }
+
+    // A comment on the line above the suppression or after it on the same
+    // line. In Rust that is a `//` comment right after the attribute. In TOML
+    // and YAML it is a `#` comment, which may come after the rest of the value
+    // or command that the suppression is part of.
+    fn adjacent_comment(&self, start: LineColumn, end: LineColumn) -> Option<String> {
+        let hash_comments = self.source_file.has_hash_comments();
+        let line = self.contents.lines().nth(end.line - 1)?;
+        let trailing = if hash_comments {
+            trailing_hash_comment(line, end.column)
+        } else {
+            let rest: String = line.chars().skip(end.column).collect();
+            rest.trim_start().strip_prefix("//").map(str::to_owned)
+        };
+        if let Some(comment) = trailing {
+            return Some(comment.trim().to_owned());
+        }
+        let line_above = self.contents.lines().nth(start.line.checked_sub(2)?)?;
+        if hash_comments {
+            let comment = line_above.trim_start().strip_prefix('#')?;
+            return Some(comment.trim().to_owned());
+        }
+        let comment = line_above.trim_start().strip_prefix("//")?;
+        if comment.starts_with(['/', '!']) {
+            // doc comment
+            return None;
+        }
+        Some(comment.trim().to_owned())
+    }
+
+    // Lints from the [lints] table of Cargo.toml.
+    fn record_manifest(&self, lints: Vec<LintAttr>) {
+        // [lints.rust] unknown_lints = "allow" covers every other lint in the
+        // table.
+        let cx = Context {
+            shim: Shim::find(&lints),
+            ..self.cx
+        };
+        for lint in lints {
+            let Span { start, end } = lint.span;
+            self.record(lint, Scope::Manifest, cx, start, end);
+        }
+    }
+
+    // Lint flags passed on the command line by a config or CI file.
+    fn record_flags(&self, flags: Vec<Flag>) {
+        let cx = Context {
+            shim: Shim::find(flags.iter().map(|flag| &flag.lint)),
+            ..self.cx
+        };
+        for flag in flags {
+            self.record(flag.lint, Scope::CommandLine, cx, flag.start, flag.end);
+        }
     }
 }
 
+// The text of a `#` comment that starts at or after the given column of the
+// line, skipping over any `#` inside of a double-quoted string.
+fn trailing_hash_comment(line: &str, column: usize) -> Option<String> {
+    let mut in_string = false;
+    let mut chars = line.chars().enumerate();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => in_string = !in_string,
+            '#' if !in_string && i >= column => return Some(chars.map(|(_i, ch)| ch).collect()),
+            _ => {}
+        }
+    }
+    None
+}
+
 #[derive(Parser)]
 #[command(version, author)]
 struct Opt {
@@ -122,36 +1003,213 @@ struct Opt {
     /// https://github.com/dtolnay/get-all-crates
     #[arg(value_name = "DIR")]
     crates_dir: PathBuf,
+
+    /// Count suppressions of a lint group, like clippy::style, against every
+    /// lint in the group.
+    #[arg(long)]
+    expand_groups: bool,
+
+    /// Write the reason or comment accompanying each suppression, grouped by
+    /// lint, to this file.
+    #[arg(long, value_name = "FILE", conflicts_with = "history")]
+    justifications: Option<PathBuf>,
+
+    /// Only count suppressions in these kinds of targets.
+    #[arg(long, value_name = "KIND", value_delimiter = ',')]
+    only_target: Vec<TargetKind>,
+
+    /// Do not count suppressions in these kinds of targets.
+    #[arg(long, value_name = "KIND", value_delimiter = ',')]
+    exclude_target: Vec<TargetKind>,
+
+    /// Do not count suppressions within #[cfg(test)] code.
+    #[arg(long)]
+    exclude_cfg_test: bool,
+
+    /// Count suppressions in generated code, like bindgen or prost output,
+    /// together with handwritten ones instead of separately.
+    #[arg(long)]
+    include_generated: bool,
+
+    /// Path to the directory extracted from the crates.io database dump.
+    /// https://static.crates.io/db-dump.tar.gz
+    #[arg(long, value_name = "DIR")]
+    db_dump: Option<PathBuf>,
+
+    /// Scan every version of each crate instead of only the newest, and
+    /// print how many crates suppress each lint per quarter.
+    #[arg(long, requires = "db_dump")]
+    history: bool,
+
+    /// Which versions of each crate may be scanned.
+    #[arg(long, value_name = "POLICY", value_enum, default_value_t)]
+    select: Policy,
+
+    /// Only scan versions published on or before this date, like 2024-12-31.
+    #[arg(long, value_name = "DATE", requires = "db_dump")]
+    as_of: Option<Date>,
+
+    /// Path to a checkout of the crates.io index, or to Cargo's cache of the
+    /// sparse index, for canonical crate names, checksums, yanked status, and
+    /// features.
+    #[arg(long, value_name = "DIR")]
+    index: Option<PathBuf>,
+
+    /// Order of the lints in each table. Sorting by downloads or dependents
+    /// requires --db-dump.
+    #[arg(long, value_name = "KEY", value_enum, default_value_t)]
+    sort: SortBy,
+
+    /// Only scan the crates.io packages in this Cargo.lock, or in this output
+    /// of `cargo metadata`, at exactly the versions listed.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["history", "select", "as_of"],
+    )]
+    lockfile: Option<PathBuf>,
 }
 
 fn main() -> Result<()> {
     let opt = Opt::parse();
 
-    // Download clippy lints.json to get group and level for every lint.
-    let lints_vec = lints::download_lint_list()?;
-    let mut lints = Map::<&str, &Lint>::new();
-    for lint in &lints_vec {
-        lints.insert(&lint.id, lint);
-        for former_id in &lint.former_ids {
-            lints.insert(former_id, lint);
+    // Download clippy lints.json, and load the bundled rustc and rustdoc lint
+    // lists, to get group and level for every lint.
+    let mut lint_lists = Map::new();
+    for namespace in Namespace::ALL {
+        lint_lists.insert(namespace, namespace.lint_list()?);
+    }
+    let mut lints = Map::new();
+    for (namespace, lint_list) in &lint_lists {
+        let lints = lints.entry(*namespace).or_insert_with(Lints::new);
+        for lint in lint_list {
+            lints.insert(&lint.id, lint);
+            for former_id in &lint.former_ids {
+                lints.insert(former_id, lint);
+            }
         }
     }
 
-    // Find the most recent version among .crate files with the same crate name.
-    let mut crate_max_versions = Map::new();
-    for entry in WalkDir::new(&opt.crates_dir) {
-        let entry = entry?;
-        if let Some((krate, version)) = parse_crate_file_path(&opt.crates_dir, entry.path()) {
-            match crate_max_versions.entry(krate) {
-                Entry::Vacant(entry) => {
-                    entry.insert(version);
-                }
-                Entry::Occupied(mut entry) => {
-                    if version > *entry.get() {
-                        entry.insert(version);
-                    }
-                }
+    let db_dump = match &opt.db_dump {
+        Some(dir) => Some(dbdump::load(dir)?),
+        None => None,
+    };
+    let index = match &opt.index {
+        Some(dir) => Some(Index::open(dir)?),
+        None => None,
+    };
+    if opt.select.needs_yanked() && db_dump.is_none() && index.is_none() {
+        bail!("--select {:?} requires --db-dump or --index", opt.select);
+    }
+    if opt.sort.needs_popularity() && db_dump.is_none() {
+        bail!("--sort {:?} requires --db-dump", opt.sort);
+    }
+
+    // Find every version among .crate files with the same crate name, or the
+    // .crate file of each package in the lockfile. The index, if available,
+    // has the canonical name of each.
+    let mut found = Vec::new();
+    let mut index_files = Map::new();
+    if let Some(lockfile) = &opt.lockfile {
+        for (krate, version) in lockfile::parse(&fs::read_to_string(lockfile)?)? {
+            let path = reconstruct_crate_file_path(&opt.crates_dir, &krate, &version);
+            if !path.is_file() {
+                eprintln!("{}: not found", path.display());
+                continue;
+            }
+            let index_entry = match &index {
+                Some(index) => find_in_index(index, &mut index_files, &path)?
+                    .and_then(|(_krate, _version, index_entry)| index_entry),
+                None => None,
+            };
+            found.push((krate, version, index_entry, path));
+        }
+    } else {
+        for entry in WalkDir::new(&opt.crates_dir) {
+            let entry = entry?;
+            let path = entry.path();
+            let in_index = match &index {
+                Some(index) => find_in_index(index, &mut index_files, path)?,
+                None => None,
+            };
+            let in_index = in_index.or_else(|| {
+                let (krate, version) = parse_crate_file_path(&opt.crates_dir, path)?;
+                Some((krate, version, None))
+            });
+            if let Some((krate, version, index_entry)) = in_index {
+                found.push((krate, version, index_entry, path.to_owned()));
+            }
+        }
+    }
+    let mut crate_versions = Map::new();
+    let mut index_entries = Map::new();
+    for (krate, version, index_entry, path) in found {
+        if let Some(index_entry) = index_entry {
+            index_entries
+                .entry(krate.clone())
+                .or_insert_with(Map::new)
+                .insert(version.clone(), index_entry);
+        }
+        crate_versions
+            .entry(krate)
+            .or_insert_with(Map::new)
+            .insert(version, path);
+    }
+
+    // Scan the most recent eligible version of each crate, or all of them.
+    // With a lockfile, scan exactly the versions in the dependency graph.
+    let mut selected = Vec::new();
+    for (krate, versions) in &crate_versions {
+        if opt.lockfile.is_some() {
+            for version in versions.keys() {
+                selected.push((krate.clone(), version.clone()));
             }
+            continue;
+        }
+        let releases = db_dump
+            .as_ref()
+            .and_then(|db_dump| db_dump.releases.get(krate));
+        let index_entries = index_entries.get(krate);
+        let versions = versions.keys().map(|version| {
+            let release = releases.and_then(|releases| releases.get(version));
+            let index_entry = index_entries.and_then(|entries| entries.get(version));
+            let info = VersionInfo {
+                created_at: release.map(|release| release.created_at),
+                yanked: index_entry
+                    .map(|entry| entry.yanked)
+                    .or(release.map(|release| release.yanked)),
+            };
+            (version, info)
+        });
+        let eligible = select::eligible(versions, opt.select, opt.as_of);
+        if opt.history {
+            for version in eligible {
+                selected.push((krate.clone(), version.clone()));
+            }
+        } else if let Some(version) = eligible.last() {
+            selected.push((krate.clone(), (*version).clone()));
+        }
+    }
+
+    // Versions that will be scanned, in the order they were published, for
+    // history.
+    let mut published = Map::new();
+    if let Some(db_dump) = &db_dump {
+        for (krate, version) in &selected {
+            let Some(release) = db_dump
+                .releases
+                .get(krate)
+                .and_then(|releases| releases.get(version))
+            else {
+                continue;
+            };
+            published
+                .entry(krate.clone())
+                .or_insert_with(Vec::new)
+                .push((release.created_at, version.clone()));
+        }
+        for versions in published.values_mut() {
+            versions.sort();
         }
     }
 
@@ -161,90 +1219,430 @@ fn main() -> Result<()> {
         .unwrap();
 
     // Parse .crate files in parallel on rayon thread pool.
-    let findings = Mutex::new(Map::new());
-    crate_max_versions
-        .into_par_iter()
-        .for_each(|(krate, version)| {
-            let path = reconstruct_crate_file_path(&opt.crates_dir, &krate, &version);
-            if let Err(err) = parse_contents(krate, version, &path, &findings, &lints) {
-                eprintln!("{}: {}", path.display(), err);
+    let collected = Collected::default();
+    selected.into_par_iter().for_each(|(krate, version)| {
+        let path = &crate_versions[&krate][&version];
+        let index_entry = index_entries
+            .get(&krate)
+            .and_then(|entries| entries.get(&version));
+        let result = if opt.history {
+            // Every version of every crate is scanned, so only which lints
+            // each one suppresses is kept, not where.
+            let scanned = Collected::default();
+            let result = parse_contents(krate, version, path, index_entry, &scanned, &lints);
+            collected.add_history(scanned, &opt, &lint_lists[&Namespace::Clippy]);
+            result
+        } else {
+            parse_contents(krate, version, path, index_entry, &collected, &lints)
+        };
+        if let Err(err) = result {
+            eprintln!("{}: {}", path.display(), err);
+        }
+    });
+
+    let mut findings: Map<Namespace, Findings> = collected.findings.into_inner();
+    let suppressing = collected.history.into_inner();
+    let enabled: Map<Namespace, Enabled> = collected.enabled.into_inner();
+    let configs = collected.configs.into_inner();
+    let file_counts = collected.file_counts.into_inner();
+    eprintln!(
+        "{} files parsed, {} scanned as tokens after failing to parse, {} unreadable, {} generated, {} not compiled",
+        file_counts.parsed,
+        file_counts.fallback,
+        file_counts.unreadable,
+        file_counts.generated,
+        file_counts.dead,
+    );
+
+    // Print markdown table of each lint's suppressions over time, in place of
+    // the tables and pages about the newest versions.
+    if opt.history {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        for (namespace, suppressing) in &suppressing {
+            let _ = writeln!(stdout, "### {} lints", namespace);
+            let _ = writeln!(stdout);
+            let _ = writeln!(
+                stdout,
+                "lint name | quarter | suppressing crates | started | stopped",
+            );
+            let _ = writeln!(stdout, "--- | --- | --- | --- | ---");
+            for (lint_id, crates) in suppressing {
+                for row in history::quarterly(crates, &published) {
+                    let _ = writeln!(
+                        stdout,
+                        "{} | {} | {} | {} | {}",
+                        namespace.lint_path(lint_id),
+                        row.quarter,
+                        row.suppressing,
+                        row.appeared,
+                        row.disappeared,
+                    );
+                }
             }
-        });
+            let _ = writeln!(stdout);
+        }
+        return Ok(());
+    }
+
+    let generated_counts = filter_findings(&mut findings, &opt);
+    if opt.expand_groups {
+        expand_groups(&mut findings, &lint_lists[&Namespace::Clippy]);
+    }
+
+    // Export justifications before limiting the number of occurrences.
+    if let Some(path) = &opt.justifications {
+        let mut out = BufWriter::new(File::create(path)?);
+        for (namespace, findings) in &findings {
+            for (lint_id, findings) in findings {
+                let mut wrote_heading = false;
+                for (source_file, locations) in findings {
+                    for (span, justification) in &locations.justifications {
+                        if !mem::replace(&mut wrote_heading, true) {
+                            writeln!(out, "## {}\n", namespace.lint_path(lint_id))?;
+                        }
+                        writeln!(
+                            out,
+                            "- {} {} {}:{} &mdash; {}",
+                            source_file.krate,
+                            source_file.version,
+                            source_file.relative_path.display(),
+                            span.start.line,
+                            justification,
+                        )?;
+                    }
+                }
+                if wrote_heading {
+                    writeln!(out)?;
+                }
+            }
+        }
+        out.flush()?;
+    }
+
+    // Count crates whose proc macros suppress each lint on behalf of their
+    // users before the limit below drops some of their occurrences.
+    let mut proc_macro_counts = Map::new();
+    for (namespace, findings) in &findings {
+        let proc_macro_counts = proc_macro_counts.entry(*namespace).or_insert_with(Map::new);
+        for (lint_id, findings) in findings {
+            let crates = findings
+                .iter()
+                .filter(|(_source_file, loc)| !loc.proc_macro.is_empty())
+                .map(|(source_file, _loc)| &source_file.krate)
+                .collect::<Set<_>>();
+            if !crates.is_empty() {
+                proc_macro_counts.insert(lint_id.clone(), crates.len());
+            }
+        }
+    }
 
     // Limit rendered occurrences per file and per crate.
     const MAX_PER_FILE: usize = 5;
     const MAX_PER_CRATE: usize = 10;
-    let mut findings = findings.into_inner();
-    for findings in findings.values_mut() {
+    for findings in findings.values_mut().flat_map(Map::values_mut) {
         let mut count_by_crate = Map::new();
         for (source_file, locations) in findings {
-            locations.global.truncate(MAX_PER_FILE);
-            locations
-                .local
-                .truncate(MAX_PER_FILE - locations.global.len());
+            locations.truncate(MAX_PER_FILE);
             let n = count_by_crate.entry(&source_file.krate).or_insert(0);
-            let remaining_for_crate = MAX_PER_CRATE - *n;
-            locations.global.truncate(remaining_for_crate);
-            locations
-                .local
-                .truncate(remaining_for_crate - locations.global.len());
-            *n += locations.global.len() + locations.local.len();
-        }
-    }
-
-    // Sort lints by how many times ignored.
-    let mut findings = Vec::from_iter(&findings);
-    findings.sort_by_cached_key(|(_lint_id, findings)| {
-        let sum: usize = findings
-            .values()
-            .map(|loc| loc.global.len() + loc.local.len())
-            .sum();
-        Reverse(sum)
-    });
+            locations.truncate(MAX_PER_CRATE - *n);
+            *n += locations.len();
+        }
+    }
+
+    // Sort lints by how many times ignored, or by how widely used the crates
+    // ignoring them are.
+    let no_popularity = Map::new();
+    let popularity = db_dump
+        .as_ref()
+        .map_or(&no_popularity, |db_dump| &db_dump.popularity);
+    let mut sorted = Map::new();
+    for (namespace, findings) in &findings {
+        let mut findings = Vec::from_iter(findings);
+        findings.sort_by_cached_key(|(_lint_id, findings)| {
+            Reverse(weight::weigh(findings, popularity).key(opt.sort))
+        });
+        sorted.insert(*namespace, findings);
+    }
+    // Columns of the popularity of crates ignoring each lint.
+    let weighted = db_dump.is_some();
+    let weighted_titles = if weighted {
+        "downloads | dependents | "
+    } else {
+        ""
+    };
+    let weighted_columns = if weighted { 2 } else { 0 };
+    // Namespaces added by --expand-groups had no findings to count generated
+    // code or proc macros in.
+    let no_counts = Map::new();
 
-    // Print markdown table of results.
     let stdout = io::stdout();
     let mut stdout = stdout.lock();
-    let _ = writeln!(stdout, "local | global | lint name | category");
-    let _ = writeln!(stdout, "--- | --- | --- | ---");
-    let site = "https://dtolnay.github.io/noisy-clippy";
-    for (lint_id, findings) in &findings {
-        let (group, level) = match lints.get(lint_id.as_str()) {
-            Some(lint) => (lint.group, lint.level),
-            None => (LintGroup::Unknown, LintLevel::None),
-        };
-        let allowed = level == LintLevel::Allow;
-        let _ = write!(stdout, "{}", if allowed { "~*" } else { "" });
-        let local: usize = findings.values().map(|loc| loc.local.len()).sum();
-        let _ = if local == 0 {
-            write!(stdout, "{}", local)
-        } else {
-            write!(stdout, "[{}]({}/{}.html#local)", local, site, lint_id)
-        };
-        let _ = write!(stdout, "{}", if allowed { "*~" } else { "" });
-        let _ = write!(stdout, " | ");
-        let _ = write!(stdout, "{}", if allowed { "~*" } else { "" });
-        let global: usize = findings.values().map(|loc| loc.global.len()).sum();
-        let _ = if global == 0 {
-            write!(stdout, "{}", global)
-        } else {
-            write!(stdout, "[{}]({}/{}.html#global)", global, site, lint_id)
-        };
-        let _ = write!(stdout, "{}", if allowed { "*~" } else { "" });
-        let _ = write!(stdout, " | ");
-        let _ = write!(stdout, "{}", if allowed { "~*" } else { "**" });
-        let clippy_index_html = "https://rust-lang.github.io/rust-clippy/master/index.html";
-        let _ = write!(stdout, "[{1}]({0}#{1})", clippy_index_html, lint_id);
-        let _ = write!(stdout, "{}", if allowed { "*~" } else { "**" });
-        let _ = write!(stdout, " | ");
-        let mut former_group = lints::former_lint_group(lint_id);
-        if former_group == Some(group) {
-            former_group = None;
-        }
-        if let Some(former_group) = former_group {
-            let _ = write!(stdout, "~*{}*~ ", former_group);
-        }
-        let _ = write!(stdout, "{}", group);
+    for (namespace, findings) in &sorted {
+        let namespace = *namespace;
+        let lints = &lints[&namespace];
+        let no_enabled = Enabled::new();
+        let enabled = enabled.get(&namespace).unwrap_or(&no_enabled);
+        if namespace != Namespace::Clippy {
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout, "### {} lints", namespace);
+            let _ = writeln!(stdout);
+        }
+
+        // Print markdown table of results.
+        let targets = Target::ALL.map(Target::title).join(" | ");
+        let _ = writeln!(
+            stdout,
+            "local | global | manifest | command line | in macro | doc example | expect | {} | enabled | {}lint name | category",
+            targets, weighted_titles,
+        );
+        let separator = vec!["---"; 10 + weighted_columns + Target::ALL.len()].join(" | ");
+        let _ = writeln!(stdout, "{}", separator);
+        // Lints that crates opt in to but never suppress come last, with no
+        // suppressions.
+        let no_findings = Map::new();
+        let suppressed = findings
+            .iter()
+            .map(|(lint_id, _findings)| *lint_id)
+            .collect::<Set<_>>();
+        let opted_in = enabled
+            .keys()
+            .filter(|lint_id| !suppressed.contains(lint_id))
+            .map(|lint_id| (lint_id, &no_findings));
+        for (lint_id, findings) in findings.iter().copied().chain(opted_in) {
+            if namespace == Namespace::Clippy && LintGroup::from_name(lint_id).is_some() {
+                continue;
+            }
+            let lint = lints.get(lint_id.as_str()).copied();
+            let group = lint.map_or(LintGroup::Unknown, |lint| lint.group);
+            let allowed = lint.is_some_and(|lint| lint.level == LintLevel::Allow);
+            // Opting in to a lint's group, e.g. clippy::pedantic, counts as
+            // opting in to the lint.
+            let group_name = group.to_string();
+            let mut names = vec![lint_id.as_str()];
+            if namespace == Namespace::Clippy {
+                names.push(&group_name);
+            }
+            let enabled = names
+                .into_iter()
+                .filter_map(|name| enabled.get(name))
+                .flatten()
+                .collect::<Set<_>>()
+                .len();
+            let columns = count_columns(findings);
+            if enabled == 0 && columns.iter().all(|(count, _anchor)| *count == 0) {
+                continue;
+            }
+            for (count, anchor) in columns {
+                write_count(&mut stdout, namespace, allowed, count, lint_id, anchor);
+            }
+            for (count, anchor) in count_targets(findings) {
+                write_count(&mut stdout, namespace, allowed, count, lint_id, &anchor);
+            }
+            let _ = write!(stdout, "{}", if allowed { "~*" } else { "" });
+            let _ = write!(stdout, "{}", enabled);
+            let _ = write!(stdout, "{}", if allowed { "*~" } else { "" });
+            let _ = write!(stdout, " | ");
+            if weighted {
+                write_weight(&mut stdout, findings, popularity);
+            }
+            write_lint(&mut stdout, namespace, allowed, lint_id, lint);
+        }
+
+        // Print markdown table of blanket suppressions of a whole lint group.
+        if namespace == Namespace::Clippy {
+            let _ = writeln!(stdout);
+            let _ = writeln!(
+                stdout,
+                "local | global | manifest | command line | in macro | doc example | expect | {} | enabled | {}lint group",
+                targets, weighted_titles,
+            );
+            let separator = vec!["---"; 9 + weighted_columns + Target::ALL.len()].join(" | ");
+            let _ = writeln!(stdout, "{}", separator);
+            for (lint_id, findings) in findings {
+                if LintGroup::from_name(lint_id).is_none() {
+                    continue;
+                }
+                let columns = count_columns(findings);
+                if columns.iter().all(|(count, _anchor)| *count == 0) {
+                    continue;
+                }
+                for (count, anchor) in columns {
+                    write_count(&mut stdout, namespace, false, count, lint_id, anchor);
+                }
+                for (count, anchor) in count_targets(findings) {
+                    write_count(&mut stdout, namespace, false, count, lint_id, &anchor);
+                }
+                let enabled = enabled.get(lint_id.as_str()).map_or(0, Set::len);
+                let _ = write!(stdout, "{} | ", enabled);
+                if weighted {
+                    write_weight(&mut stdout, findings, popularity);
+                }
+                let _ = writeln!(stdout, "**clippy::{}**", lint_id);
+            }
+
+            // Print markdown table of lints turned back off by crates that
+            // opted in to the lint's whole group.
+            let _ = writeln!(stdout);
+            let _ = writeln!(
+                stdout,
+                "opted in to group | turned back off | rate | lint name | category",
+            );
+            let _ = writeln!(stdout, "--- | --- | --- | --- | ---");
+            for opt_out in opt_outs(findings.iter().copied(), enabled, lints) {
+                let lint = lints.get(opt_out.lint_id).copied();
+                let allowed = lint.is_some_and(|lint| lint.level == LintLevel::Allow);
+                let _ = write!(stdout, "{} | ", opt_out.opted_in);
+                write_count(
+                    &mut stdout,
+                    namespace,
+                    allowed,
+                    opt_out.turned_off,
+                    opt_out.lint_id,
+                    "global",
+                );
+                let permille = opt_out.turned_off * 1000 / opt_out.opted_in;
+                let _ = write!(stdout, "{}.{}% | ", permille / 10, permille % 10);
+                write_lint(&mut stdout, namespace, allowed, opt_out.lint_id, lint);
+            }
+
+            // Print markdown table of suppressions that are paired with an
+            // allow of unknown_lints or renamed_and_removed_lints, so that
+            // toolchains older than the lint, or than its current name,
+            // accept them.
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout, "### MSRV workarounds");
+            let _ = writeln!(stdout);
+            let _ = writeln!(
+                stdout,
+                "with unknown_lints | with renamed_and_removed_lints | former names | lint name | category",
+            );
+            let _ = writeln!(stdout, "--- | --- | --- | --- | ---");
+            for (lint_id, findings) in findings {
+                let count = |shim: Shim| {
+                    findings
+                        .values()
+                        .flat_map(|loc| loc.spans().filter_map(|span| loc.shims.get(span)))
+                        .filter(|span_shim| **span_shim == shim)
+                        .count()
+                };
+                let unknown_lints = count(Shim::UnknownLints);
+                let renamed_and_removed_lints = count(Shim::RenamedAndRemovedLints);
+                if unknown_lints + renamed_and_removed_lints == 0 {
+                    continue;
+                }
+                let lint = lints.get(lint_id.as_str()).copied();
+                let allowed = lint.is_some_and(|lint| lint.level == LintLevel::Allow);
+                // Lints that were renamed are the ones whose old name an older
+                // toolchain may be the only one to know.
+                let former_ids = lint.map_or_else(Vec::new, |lint| {
+                    lint.former_ids.iter().map(String::as_str).collect()
+                });
+                let _ = write!(
+                    stdout,
+                    "{} | {} | {} | ",
+                    unknown_lints,
+                    renamed_and_removed_lints,
+                    former_ids.join(", "),
+                );
+                write_lint(&mut stdout, namespace, allowed, lint_id, lint);
+            }
+
+            // Print markdown table of crates that still refer to a lint by a
+            // name it had before being renamed.
+            let mut former_id_crates = Map::new();
+            for (lint_id, findings) in findings {
+                for (source_file, loc) in *findings {
+                    for former_id in loc.spans().filter_map(|span| loc.former_ids.get(span)) {
+                        former_id_crates
+                            .entry((former_id.as_str(), lint_id.as_str()))
+                            .or_insert_with(Set::new)
+                            .insert(&source_file.krate);
+                    }
+                }
+            }
+            let mut former_id_crates = Vec::from_iter(former_id_crates);
+            former_id_crates.sort_by_key(|(_ids, crates)| Reverse(crates.len()));
+            let _ = writeln!(stdout);
+            let _ = writeln!(stdout, "crates | former name | lint name | category");
+            let _ = writeln!(stdout, "--- | --- | --- | ---");
+            for ((former_id, lint_id), crates) in former_id_crates {
+                let lint = lints.get(lint_id).copied();
+                let allowed = lint.is_some_and(|lint| lint.level == LintLevel::Allow);
+                let _ = write!(stdout, "{} | clippy::{} | ", crates.len(), former_id);
+                write_lint(&mut stdout, namespace, allowed, lint_id, lint);
+            }
+        }
+
+        // Print markdown table of lints suppressed by proc macros in the code
+        // they generate, on behalf of the macro's users.
+        let mut proc_macro_findings = Vec::from_iter(
+            proc_macro_counts
+                .get(&namespace)
+                .map_or(&no_counts, |counts| counts),
+        );
+        proc_macro_findings.sort_by_key(|(_lint_id, count)| Reverse(**count));
+        let _ = writeln!(stdout);
+        let _ = writeln!(
+            stdout,
+            "suppressed on behalf of users | lint name | category"
+        );
+        let _ = writeln!(stdout, "--- | --- | ---");
+        for (lint_id, count) in proc_macro_findings {
+            let lint = lints.get(lint_id.as_str()).copied();
+            let allowed = lint.is_some_and(|lint| lint.level == LintLevel::Allow);
+            write_count(
+                &mut stdout,
+                namespace,
+                allowed,
+                *count,
+                lint_id,
+                "proc-macro",
+            );
+            write_lint(&mut stdout, namespace, allowed, lint_id, lint);
+        }
+
+        // Print markdown table of suppressions in generated code.
+        let mut generated_counts = Vec::from_iter(
+            generated_counts
+                .get(&namespace)
+                .map_or(&no_counts, |counts| counts),
+        );
+        generated_counts.sort_by_key(|(_lint_id, count)| Reverse(**count));
+        let _ = writeln!(stdout);
+        let _ = writeln!(stdout, "in generated code | lint name | category");
+        let _ = writeln!(stdout, "--- | --- | ---");
+        for (lint_id, count) in generated_counts {
+            let lint = lints.get(lint_id.as_str()).copied();
+            let allowed = lint.is_some_and(|lint| lint.level == LintLevel::Allow);
+            let _ = write!(stdout, "{}", if allowed { "~*" } else { "" });
+            let _ = write!(stdout, "{}", count);
+            let _ = write!(stdout, "{}", if allowed { "*~" } else { "" });
+            let _ = write!(stdout, " | ");
+            write_lint(&mut stdout, namespace, allowed, lint_id, lint);
+        }
+    }
+
+    // Print markdown table of clippy.toml options.
+    let mut options = Vec::from_iter(&configs);
+    options.sort_by_key(|(_option, values)| Reverse(values.len()));
+    let _ = writeln!(stdout);
+    let _ = writeln!(stdout, "### clippy.toml");
+    let _ = writeln!(stdout);
+    let _ = writeln!(stdout, "crates | clippy.toml option | values");
+    let _ = writeln!(stdout, "--- | --- | ---");
+    for (option, values) in options {
+        let mut distribution = Map::new();
+        for value in values.values() {
+            *distribution.entry(value).or_insert(0) += 1;
+        }
+        let mut distribution = Vec::from_iter(distribution);
+        distribution.sort_by_key(|(_value, count)| Reverse(*count));
+        let _ = write!(stdout, "{} | `{}` | ", values.len(), option);
+        for (i, (value, count)) in distribution.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            let _ = write!(stdout, "{}`{}`&nbsp;({})", sep, value, count);
+        }
         let _ = writeln!(stdout);
     }
 
@@ -262,11 +1660,31 @@ fn main() -> Result<()> {
         let tree_entries = None;
         let mut builder = repo.treebuilder(tree_entries)?;
         let filemode = u32::from(FileMode::Blob) as i32;
-        for (lint_id, findings) in &findings {
-            let html = render(lint_id, findings);
-            let filename = format!("{}.html", lint_id);
-            let oid = repo.blob(html.as_bytes())?;
-            builder.insert(filename, oid, filemode)?;
+        for (namespace, findings) in &sorted {
+            // Pages of lints other than Clippy's go in a subdirectory per
+            // namespace.
+            let mut subdir = match namespace {
+                Namespace::Clippy => None,
+                Namespace::Rustc | Namespace::Rustdoc => Some(repo.treebuilder(tree_entries)?),
+            };
+            for (lint_id, findings) in findings {
+                let tuned = match namespace {
+                    Namespace::Clippy => config::crates_tuning(lint_id, &configs).len(),
+                    Namespace::Rustc | Namespace::Rustdoc => 0,
+                };
+                let html = render(*namespace, lint_id, findings, tuned);
+                let filename = format!("{}.html", lint_id);
+                let oid = repo.blob(html.as_bytes())?;
+                subdir
+                    .as_mut()
+                    .unwrap_or(&mut builder)
+                    .insert(filename, oid, filemode)?;
+            }
+            if let Some(subdir) = subdir {
+                let oid = subdir.write()?;
+                let filemode = u32::from(FileMode::Tree) as i32;
+                builder.insert(namespace.to_string(), oid, filemode)?;
+            }
         }
 
         let oid = repo.blob(include_bytes!("style.css"))?;
@@ -302,6 +1720,221 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+// Leaves out the kinds of code that were not asked for. Suppressions in
+// generated code are counted separately, and returned.
+fn filter_findings(
+    findings: &mut Map<Namespace, Findings>,
+    opt: &Opt,
+) -> Map<Namespace, Map<String, usize>> {
+    let mut generated_counts = Map::new();
+    for (namespace, findings) in findings {
+        let generated_counts = generated_counts.entry(*namespace).or_insert_with(Map::new);
+        for (lint_id, lint_findings) in findings.iter_mut() {
+            lint_findings.retain(|source_file, locations| {
+                let kind = source_file.target_kind;
+                if !opt.only_target.is_empty() && !opt.only_target.contains(&kind)
+                    || opt.exclude_target.contains(&kind)
+                {
+                    return false;
+                }
+                if opt.exclude_cfg_test {
+                    let cfg_test = mem::take(&mut locations.cfg_test);
+                    locations.retain(|span| !cfg_test.contains(span));
+                }
+                let generated = locations
+                    .spans()
+                    .filter(|span| locations.generated.contains(span));
+                let generated_count = generated.count();
+                if generated_count > 0 {
+                    *generated_counts.entry(lint_id.clone()).or_insert(0) += generated_count;
+                    if !opt.include_generated {
+                        let generated = mem::take(&mut locations.generated);
+                        locations.retain(|span| !generated.contains(span));
+                    }
+                }
+                locations.len() > 0
+            });
+        }
+        findings.retain(|_lint_id, lint_findings| !lint_findings.is_empty());
+    }
+    generated_counts
+}
+
+// Counts suppression of a Clippy lint group against each lint in the group.
+fn expand_groups(findings: &mut Map<Namespace, Findings>, clippy_lints: &[Lint]) {
+    let findings = findings.entry(Namespace::Clippy).or_insert_with(Map::new);
+    let mut expanded = Vec::new();
+    for (lint_id, group_findings) in &*findings {
+        let Some(group) = LintGroup::from_name(lint_id) else {
+            continue;
+        };
+        for lint in clippy_lints {
+            if group.contains(lint.group) {
+                expanded.push((lint.id.clone(), group_findings.clone()));
+            }
+        }
+    }
+    for (lint_id, group_findings) in expanded {
+        let lint_findings = findings.entry(lint_id).or_insert_with(Map::new);
+        for (source_file, group_locations) in group_findings {
+            match lint_findings.entry(source_file) {
+                Entry::Vacant(entry) => {
+                    entry.insert(group_locations);
+                }
+                Entry::Occupied(mut entry) => entry.get_mut().extend(&group_locations),
+            }
+        }
+    }
+}
+
+// Number of suppressions in each column of the table, and the anchor of the
+// corresponding section of the lint's page.
+fn count_columns(findings: &Map<SourceFile, Locations>) -> [(usize, &'static str); 7] {
+    let count =
+        |spans: fn(&Locations) -> &Vec<Span>| findings.values().map(|loc| spans(loc).len()).sum();
+    [
+        (count(|loc| &loc.local), "local"),
+        (count(|loc| &loc.global), "global"),
+        (count(|loc| &loc.manifest), "manifest"),
+        (count(|loc| &loc.command_line), "command-line"),
+        (count(|loc| &loc.in_macro), "macro"),
+        (count(|loc| &loc.doc_example), "doc-example"),
+        (count(|loc| &loc.expect), "expect"),
+    ]
+}
+
+// A lint in a group that is off by default, and how many of the crates that
+// opted in to the whole group allow that one lint.
+struct OptOut<'a> {
+    lint_id: &'a str,
+    opted_in: usize,
+    turned_off: usize,
+}
+
+fn opt_outs<'a>(
+    findings: impl IntoIterator<Item = (&'a String, &'a Map<SourceFile, Locations>)>,
+    enabled: &Enabled,
+    lints: &Lints,
+) -> Vec<OptOut<'a>> {
+    let no_crates = Set::new();
+    let mut opt_outs = Vec::new();
+    for (lint_id, findings) in findings {
+        let Some(lint) = lints.get(lint_id.as_str()) else {
+            continue;
+        };
+        if !matches!(
+            lint.group,
+            LintGroup::Pedantic | LintGroup::Nursery | LintGroup::Restriction,
+        ) {
+            continue;
+        }
+        let opted_in = enabled.get(&lint.group.to_string()).unwrap_or(&no_crates);
+        if opted_in.is_empty() {
+            continue;
+        }
+        // Allows inside of doc examples or emitted by a proc macro do not
+        // turn the lint off for the crate itself.
+        let turned_off = findings
+            .iter()
+            .filter(|(_source_file, loc)| loc.len() > loc.doc_example.len() + loc.proc_macro.len())
+            .map(|(source_file, _loc)| &source_file.krate)
+            .filter(|krate| opted_in.contains(krate))
+            .collect::<Set<_>>();
+        if !turned_off.is_empty() {
+            opt_outs.push(OptOut {
+                lint_id,
+                opted_in: opted_in.len(),
+                turned_off: turned_off.len(),
+            });
+        }
+    }
+    // Highest rate first.
+    opt_outs.sort_by(|a, b| (b.turned_off * a.opted_in).cmp(&(a.turned_off * b.opted_in)));
+    opt_outs
+}
+
+// Number of suppressions applying to each kind of item, and the anchor that
+// filters the lint's page down to those.
+fn count_targets(findings: &Map<SourceFile, Locations>) -> Vec<(usize, String)> {
+    Target::ALL
+        .into_iter()
+        .map(|target| {
+            let count = findings
+                .values()
+                .flat_map(|loc| loc.spans().map(|span| loc.targets.get(span)))
+                .filter(|span_target| *span_target == Some(&target))
+                .count();
+            (count, format!("target={}", target.name()))
+        })
+        .collect()
+}
+
+fn write_count(
+    stdout: &mut dyn Write,
+    namespace: Namespace,
+    allowed: bool,
+    count: usize,
+    lint_id: &str,
+    anchor: &str,
+) {
+    let site = "https://dtolnay.github.io/noisy-clippy";
+    let page = namespace.page(lint_id);
+    let _ = write!(stdout, "{}", if allowed { "~*" } else { "" });
+    let _ = if count == 0 {
+        write!(stdout, "{}", count)
+    } else {
+        write!(stdout, "[{}]({}/{}#{})", count, site, page, anchor)
+    };
+    let _ = write!(stdout, "{}", if allowed { "*~" } else { "" });
+    let _ = write!(stdout, " | ");
+}
+
+fn write_weight(
+    stdout: &mut dyn Write,
+    findings: &Map<SourceFile, Locations>,
+    popularity: &Map<Crate, Popularity>,
+) {
+    let weight = weight::weigh(findings, popularity);
+    let _ = write!(stdout, "{} | {} | ", weight.downloads, weight.dependents);
+}
+
+fn write_lint(
+    stdout: &mut dyn Write,
+    namespace: Namespace,
+    allowed: bool,
+    lint_id: &str,
+    lint: Option<&Lint>,
+) {
+    let level = lint.map_or(LintLevel::None, |lint| lint.level);
+    let _ = write!(stdout, "{}", if allowed { "~*" } else { "**" });
+    let docs_url = namespace.docs_url(lint_id, level);
+    let _ = write!(stdout, "[{}]({})", lint_id, docs_url);
+    let _ = write!(stdout, "{}", if allowed { "*~" } else { "**" });
+    let _ = write!(stdout, " | ");
+    if namespace != Namespace::Clippy {
+        // Rustc and rustdoc lints have no category; show the default level.
+        let category = match (lint, level) {
+            (None, _) => "unknown",
+            (Some(_), LintLevel::Allow) => "allow",
+            (Some(_), LintLevel::Warn) => "warn",
+            (Some(_), LintLevel::Deny) => "deny",
+            (Some(_), LintLevel::None) => "group",
+        };
+        let _ = writeln!(stdout, "{}", category);
+        return;
+    }
+    let group = lint.map_or(LintGroup::Unknown, |lint| lint.group);
+    let mut former_group = lints::former_lint_group(lint_id);
+    if former_group == Some(group) {
+        former_group = None;
+    }
+    if let Some(former_group) = former_group {
+        let _ = write!(stdout, "~*{}*~ ", former_group);
+    }
+    let _ = write!(stdout, "{}", group);
+    let _ = writeln!(stdout);
+}
+
 fn parse_crate_file_path(crates_dir: &Path, path: &Path) -> Option<(Crate, Version)> {
     let extension = path.extension()?;
     if extension != "crate" {
@@ -326,60 +1959,225 @@ fn reconstruct_crate_file_path(
     crate_name: &Crate,
     version: &Version,
 ) -> PathBuf {
-    let mut path = crates_dir.to_owned();
-    let name_lower = crate_name.to_ascii_lowercase();
-    match name_lower.len() {
-        1 => path.push("1"),
-        2 => path.push("2"),
-        3 => path.extend(["3", &name_lower[..1]]),
-        _ => path.extend([&name_lower[0..2], &name_lower[2..4]]),
-    }
-    path.push(name_lower);
+    let mut path = crates_dir.join(index::relative_path(&crate_name.to_ascii_lowercase()));
     path.push(format!("{}-{}.crate", crate_name, version));
     path
 }
 
+// The index entry whose name and version match the .crate file name, which
+// may differ in case from the canonical name of the crate. Index files are
+// read once per crate, keyed by the lowercase name that is the .crate file's
+// directory.
+fn find_in_index(
+    index: &Index,
+    index_files: &mut Map<String, Vec<IndexEntry>>,
+    path: &Path,
+) -> Result<Option<(Crate, Version, Option<IndexEntry>)>> {
+    if path.extension() != Some(OsStr::new("crate")) {
+        return Ok(None);
+    }
+    let (Some(file_name), Some(name_lower)) = (
+        path.file_name().and_then(OsStr::to_str),
+        path.parent()
+            .and_then(Path::file_name)
+            .and_then(OsStr::to_str),
+    ) else {
+        return Ok(None);
+    };
+    let entries = match index_files.entry(name_lower.to_owned()) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => entry.insert(index.entries(name_lower)?),
+    };
+    for entry in entries.iter() {
+        let expected = format!("{}-{}.crate", entry.name, entry.vers);
+        if expected.eq_ignore_ascii_case(file_name) {
+            let krate = Crate::new(entry.name.clone());
+            return Ok(Some((krate, entry.vers.clone(), Some(entry.clone()))));
+        }
+    }
+    Ok(None)
+}
+
 fn parse_contents(
     krate: Crate,
     version: Version,
     path: &Path,
-    findings: &Mutex<Findings>,
-    lints: &Map<&str, &Lint>,
+    index_entry: Option<&IndexEntry>,
+    collected: &Collected,
+    lints: &Map<Namespace, Lints>,
 ) -> Result<()> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let tar = GzDecoder::new(reader);
+    let crate_file = fs::read(path)?;
+    if let Some(index_entry) = index_entry {
+        index_entry.verify(&crate_file)?;
+    }
+    let tar = GzDecoder::new(crate_file.as_slice());
     let mut archive = Archive::new(tar);
-    let mut source_file = SourceFile {
-        krate,
-        version,
-        relative_path: PathBuf::new(),
-    };
+    let features = index_entry.map(IndexEntry::feature_names);
+
+    // Read everything relevant out of the archive first, so that the manifest
+    // is available regardless of the order of entries in the tarball.
+    let mut files = Map::new();
+    // Markdown, which may be included into the documentation of the crate.
+    let mut docs = Map::new();
+    // Configuration that passes lint levels on the command line.
+    let mut flags_files = Map::new();
     for entry in archive.entries()? {
         let mut entry = entry?;
         if entry.size() > 10 * 1024 * 1024 {
             continue;
         }
         let path = entry.path()?;
-        if path.extension() != Some(OsStr::new("rs")) {
+        let relative_path: PathBuf = path.iter().skip(1).collect();
+        if flags::is_flags_file(&relative_path) {
+            let mut contents = String::new();
+            if entry.read_to_string(&mut contents).is_ok() {
+                flags_files.insert(relative_path, contents);
+            }
             continue;
         }
-        let path = path.into_owned();
-        let mut contents = String::new();
-        if entry.read_to_string(&mut contents).is_err() {
+        if path.extension() == Some(OsStr::new("md")) {
+            let mut contents = String::new();
+            if entry.read_to_string(&mut contents).is_ok() {
+                docs.insert(relative_path, contents);
+            }
+            continue;
+        }
+        if relative_path != Path::new("Cargo.toml")
+            && relative_path != Path::new("clippy.toml")
+            && relative_path != Path::new(".clippy.toml")
+            && path.extension() != Some(OsStr::new("rs"))
+        {
+            continue;
+        }
+        let mut contents = Vec::new();
+        if entry.read_to_end(&mut contents).is_err() {
             break;
         }
-        let Ok(syn) = syn::parse_file(&contents) else {
+        let Ok(contents) = String::from_utf8(contents) else {
+            collected.file_counts.lock().add(FileStatus::Unreadable);
             continue;
         };
-        source_file.relative_path = path.iter().skip(1).collect();
-        let mut visitor = AttrVisitor {
-            source_file: &source_file,
-            contents: Arc::new(contents),
-            findings,
-            lints,
+        files.insert(relative_path, contents);
+    }
+
+    let manifest = files
+        .remove(Path::new("Cargo.toml"))
+        .and_then(|contents| Some((manifest::parse(&contents).ok()?, contents)));
+    let (explicit_targets, build_script) = match &manifest {
+        Some((manifest, _contents)) => (manifest.targets.clone(), manifest.build_script),
+        None => (Vec::new(), true),
+    };
+    let targets = Targets::new(
+        explicit_targets,
+        files.contains_key(Path::new("src/lib.rs")),
+        build_script,
+    );
+
+    let mut source_file = SourceFile {
+        krate,
+        version,
+        relative_path: PathBuf::new(),
+        target_kind: targets.primary(),
+    };
+    for config_path in ["clippy.toml", ".clippy.toml"] {
+        let Some(contents) = files.remove(Path::new(config_path)) else {
+            continue;
+        };
+        let Ok(options) = config::options(&contents) else {
+            continue;
+        };
+        config::tally(&mut collected.configs.lock(), &source_file.krate, options);
+    }
+    // Each file is parsed once to resolve the module tree and again when it is
+    // visited, rather than keeping the syntax tree of every file of the crate.
+    let outlines: Map<PathBuf, Outline> = files
+        .iter()
+        .map(|(relative_path, contents)| (relative_path.clone(), Outline::parse(contents)))
+        .collect();
+    let roots = targets.roots(outlines.keys());
+    let modules = ModuleTree::resolve(roots, outlines);
+
+    let scan = CrateScan {
+        collected,
+        lints,
+        proc_macro: manifest
+            .as_ref()
+            .is_some_and(|(manifest, _contents)| manifest.proc_macro),
+        features: features.as_ref(),
+        modules: &modules,
+    };
+    if let Some((manifest, contents)) = manifest {
+        source_file.relative_path = PathBuf::from("Cargo.toml");
+        let visitor = scan.visitor(
+            &source_file,
+            contents,
+            Context {
+                target: Target::CrateRoot,
+                governed: modules.all(),
+                ..Context::default()
+            },
+        );
+        visitor.record_manifest(manifest.lints);
+    }
+    for (relative_path, contents) in flags_files {
+        let flags = flags::parse(&contents, &lints[&Namespace::Rustc]);
+        if flags.is_empty() {
+            continue;
+        }
+        source_file.target_kind = targets.primary();
+        source_file.relative_path = relative_path;
+        let visitor = scan.visitor(
+            &source_file,
+            contents,
+            Context {
+                target: Target::CrateRoot,
+                governed: modules.all(),
+                ..Context::default()
+            },
+        );
+        visitor.record_flags(flags);
+    }
+    let mut doc_includes = Map::new();
+    for (relative_path, contents) in files {
+        // Files left over from before a refactor, or only used by some
+        // unpublished tooling, are never compiled. If the tree could not be
+        // resolved at all, every file is kept.
+        if !modules.is_empty() && !modules.is_live(&relative_path) {
+            collected.file_counts.lock().dead += 1;
+            continue;
+        }
+        let generated = generated::is_generated_file(&relative_path, &contents);
+        if generated {
+            collected.file_counts.lock().generated += 1;
+        }
+        // Files declared by `#[cfg(test)] mod tests;` and their submodules.
+        let cfg_test = modules.is_cfg_test(&relative_path);
+        let source = Source::parse(&contents);
+        source_file.target_kind = targets.kind(&relative_path);
+        source_file.relative_path = relative_path;
+        let mut visitor = scan.visitor(
+            &source_file,
+            contents,
+            Context {
+                cfg_test,
+                generated,
+                ..Context::default()
+            },
+        );
+        let status = visitor.scan_file(&source);
+        collected.file_counts.lock().add(status);
+        for path in visitor.doc_includes {
+            doc_includes.entry(path).or_insert(source_file.target_kind);
+        }
+    }
+    for (relative_path, target_kind) in doc_includes {
+        let Some(contents) = docs.remove(&relative_path) else {
+            continue;
         };
-        visitor.visit_file(&syn);
+        source_file.target_kind = target_kind;
+        source_file.relative_path = relative_path;
+        let mut visitor = scan.visitor(&source_file, contents, Context::default());
+        visitor.scan_markdown();
     }
     Ok(())
 }
@@ -0,0 +1,179 @@
+use crate::lints::Namespace;
+use crate::parse::{Level, LintAttr};
+use crate::target::TargetKind;
+use crate::Span;
+use proc_macro2::LineColumn;
+use serde_derive::Deserialize;
+use std::collections::BTreeMap as Map;
+use std::ops::Range;
+use std::path::PathBuf;
+use toml::Spanned;
+
+pub(crate) struct Manifest {
+    pub lints: Vec<LintAttr>,
+    pub proc_macro: bool,
+    // Targets whose root source file is given explicitly.
+    pub targets: Vec<(TargetKind, PathBuf)>,
+    // False if the package says `build = false`, so that a build.rs is not
+    // compiled.
+    pub build_script: bool,
+}
+
+#[derive(Deserialize)]
+struct TomlManifest {
+    #[serde(default)]
+    package: Package,
+    #[serde(default)]
+    lib: Lib,
+    #[serde(default)]
+    bin: Vec<Target>,
+    #[serde(default)]
+    test: Vec<Target>,
+    #[serde(default)]
+    example: Vec<Target>,
+    #[serde(default)]
+    bench: Vec<Target>,
+    #[serde(default)]
+    lints: Lints,
+    #[serde(default)]
+    workspace: Workspace,
+}
+
+#[derive(Deserialize, Default)]
+struct Package {
+    // Either a path or a bool.
+    build: Option<toml::Value>,
+}
+
+#[derive(Deserialize, Default)]
+struct Lib {
+    #[serde(rename = "proc-macro", alias = "proc_macro", default)]
+    proc_macro: bool,
+    path: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    path: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default)]
+struct Workspace {
+    #[serde(default)]
+    lints: Lints,
+}
+
+#[derive(Deserialize, Default)]
+struct Lints {
+    #[serde(default)]
+    clippy: Map<Spanned<String>, LintConfig>,
+    #[serde(default)]
+    rust: Map<Spanned<String>, LintConfig>,
+    #[serde(default)]
+    rustdoc: Map<Spanned<String>, LintConfig>,
+}
+
+impl Lints {
+    fn by_namespace(self) -> impl Iterator<Item = (Namespace, Spanned<String>, LintConfig)> {
+        let clippy = self
+            .clippy
+            .into_iter()
+            .map(|(k, v)| (Namespace::Clippy, k, v));
+        let rust = self.rust.into_iter().map(|(k, v)| (Namespace::Rustc, k, v));
+        let rustdoc = self
+            .rustdoc
+            .into_iter()
+            .map(|(k, v)| (Namespace::Rustdoc, k, v));
+        clippy.chain(rust).chain(rustdoc)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LintConfig {
+    Level(String),
+    Table { level: String },
+}
+
+// [lib]
+// proc-macro = true
+// path = "src/lib.rs"
+//
+// [[bin]]
+// path = "src/main.rs"
+//
+// [lints.clippy]
+// lint_id = "allow"
+// lint_id = { level = "allow", priority = 1 }
+//
+// and likewise [lints.rust] and [lints.rustdoc].
+pub(crate) fn parse(contents: &str) -> Result<Manifest, toml::de::Error> {
+    let manifest: TomlManifest = toml::from_str(contents)?;
+
+    let mut lints = Vec::new();
+    for (namespace, lint_id, config) in manifest
+        .lints
+        .by_namespace()
+        .chain(manifest.workspace.lints.by_namespace())
+    {
+        let (LintConfig::Level(level) | LintConfig::Table { level }) = config;
+        if let Some(level) = Level::from_name(&level) {
+            let span = span(contents, lint_id.span());
+            lints.push(LintAttr {
+                level,
+                namespace,
+                lint_id: lint_id.into_inner(),
+                span,
+                reason: None,
+            });
+        }
+    }
+
+    let mut targets = Vec::new();
+    let mut build_script = true;
+    match manifest.package.build {
+        Some(toml::Value::String(path)) => {
+            targets.push((TargetKind::BuildScript, PathBuf::from(path)));
+        }
+        Some(toml::Value::Boolean(false)) => build_script = false,
+        _ => {}
+    }
+    if let Some(path) = manifest.lib.path {
+        targets.push((TargetKind::Lib, path));
+    }
+    for (kind, list) in [
+        (TargetKind::Bin, manifest.bin),
+        (TargetKind::Test, manifest.test),
+        (TargetKind::Example, manifest.example),
+        (TargetKind::Bench, manifest.bench),
+    ] {
+        for target in list {
+            if let Some(path) = target.path {
+                targets.push((kind, path));
+            }
+        }
+    }
+
+    Ok(Manifest {
+        lints,
+        proc_macro: manifest.lib.proc_macro,
+        targets,
+        build_script,
+    })
+}
+
+fn span(contents: &str, range: Range<usize>) -> Span {
+    Span {
+        start: line_column(contents, range.start),
+        end: line_column(contents, range.end),
+    }
+}
+
+fn line_column(contents: &str, offset: usize) -> LineColumn {
+    let before = &contents[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    LineColumn {
+        line: 1 + before.matches('\n').count(),
+        column: before[line_start..].chars().count(),
+    }
+}
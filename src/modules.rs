@@ -0,0 +1,442 @@
+use crate::parse;
+use proc_macro2::{TokenStream, TokenTree};
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::path::{Component, Path, PathBuf};
+use syn::visit::{self, Visit};
+use syn::{Attribute, Expr, ExprLit, ImplItem, Item, ItemMod, Lit, Meta, TraitItem};
+
+// A source file, parsed as far as possible.
+pub(crate) enum Source {
+    Syntax(syn::File),
+    // Nightly syntax, 2015 edition code, or anything else syn rejects.
+    Tokens(TokenStream),
+    Unreadable,
+}
+
+impl Source {
+    pub(crate) fn parse(contents: &str) -> Self {
+        if let Ok(syntax) = syn::parse_file(contents) {
+            Source::Syntax(syntax)
+        } else if let Ok(tokens) = contents.parse::<TokenStream>() {
+            Source::Tokens(tokens)
+        } else {
+            Source::Unreadable
+        }
+    }
+}
+
+// What the module tree needs from a source file, kept after its syntax tree
+// is dropped.
+#[derive(Default)]
+pub(crate) struct Outline {
+    decls: Vec<Decl>,
+    // Number of items in the file, including nested ones.
+    items: usize,
+}
+
+// A `mod m;` or include! in a file.
+struct Decl {
+    // Inline modules that the declaration is inside of, by name.
+    idents: Vec<String>,
+    // The same inline modules by directory name, which #[path] may change.
+    dirs: Vec<String>,
+    // Whether the declaration, or an inline module it is inside of, is
+    // #[cfg(test)].
+    cfg_test: bool,
+    kind: DeclKind,
+}
+
+enum DeclKind {
+    Mod {
+        name: String,
+        path_attr: Option<String>,
+    },
+    Include(String),
+}
+
+impl Outline {
+    pub(crate) fn parse(contents: &str) -> Self {
+        Outline::of(&Source::parse(contents))
+    }
+
+    fn of(source: &Source) -> Self {
+        let mut walker = OutlineWalker {
+            idents: Vec::new(),
+            dirs: Vec::new(),
+            cfg_test: false,
+            decls: Vec::new(),
+        };
+        let items = match source {
+            Source::Syntax(syntax) => {
+                walker.visit_file(syntax);
+                count_items(|counter| counter.visit_file(syntax))
+            }
+            Source::Tokens(tokens) => {
+                walker.scan_tokens(tokens.clone());
+                0
+            }
+            Source::Unreadable => 0,
+        };
+        Outline {
+            decls: walker.decls,
+            items,
+        }
+    }
+}
+
+// Which files of a package are compiled, as found by following `mod` and
+// `include!` from the root of each target.
+#[derive(Default)]
+pub(crate) struct ModuleTree {
+    roots: Set<PathBuf>,
+    // Every file of the package.
+    outlines: Map<PathBuf, Outline>,
+    // Every reachable file, and the files it declares as modules or includes.
+    children: Map<PathBuf, Vec<PathBuf>>,
+    // Files that are reachable without going through a #[cfg(test)]
+    // declaration, and so are compiled outside of tests.
+    non_test: Set<PathBuf>,
+}
+
+// How much code a suppression applies to.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub(crate) struct Governed {
+    // Source files that the suppression applies to in their entirety.
+    pub files: usize,
+    // Items, like functions or impl blocks, including nested ones.
+    pub items: usize,
+}
+
+impl ModuleTree {
+    pub(crate) fn resolve(roots: Vec<PathBuf>, outlines: Map<PathBuf, Outline>) -> Self {
+        let mut tree = ModuleTree {
+            outlines,
+            ..ModuleTree::default()
+        };
+        let mut queue = Vec::new();
+        for root in roots {
+            if tree.outlines.contains_key(&root) {
+                tree.roots.insert(root.clone());
+                queue.push(root);
+            }
+        }
+        while let Some(path) = queue.pop() {
+            if tree.children.contains_key(&path) {
+                continue;
+            }
+            let mut children = Vec::new();
+            for decl in &tree.outlines[&path].decls {
+                if let Some(child) = tree.resolve_decl(&path, decl) {
+                    if !children.contains(&child) {
+                        children.push(child);
+                    }
+                }
+            }
+            queue.extend(children.iter().cloned());
+            tree.children.insert(path, children);
+        }
+        // Again, without following #[cfg(test)] declarations.
+        let mut queue = tree.roots.iter().cloned().collect::<Vec<_>>();
+        while let Some(path) = queue.pop() {
+            if !tree.non_test.insert(path.clone()) {
+                continue;
+            }
+            for decl in &tree.outlines[&path].decls {
+                if !decl.cfg_test {
+                    queue.extend(tree.resolve_decl(&path, decl));
+                }
+            }
+        }
+        tree
+    }
+
+    pub(crate) fn is_root(&self, path: &Path) -> bool {
+        self.roots.contains(path)
+    }
+
+    pub(crate) fn is_live(&self, path: &Path) -> bool {
+        self.children.contains_key(path)
+    }
+
+    // Files that are only compiled for tests, like one declared by
+    // `#[cfg(test)] mod tests;`.
+    pub(crate) fn is_cfg_test(&self, path: &Path) -> bool {
+        self.is_live(path) && !self.non_test.contains(path)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    // Every reachable file.
+    pub(crate) fn all(&self) -> Governed {
+        Governed {
+            files: self.children.len(),
+            items: self.children.keys().map(|path| self.items(path)).sum(),
+        }
+    }
+
+    // Files declared by the given file, transitively, not counting itself.
+    pub(crate) fn descendants(&self, path: &Path) -> Governed {
+        let mut visited = Set::new();
+        let mut queue = self
+            .children
+            .get(path)
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        while let Some(child) = queue.pop() {
+            if child != path && visited.insert(child) {
+                queue.extend(self.children.get(child).into_iter().flatten());
+            }
+        }
+        Governed {
+            files: visited.len(),
+            items: visited.iter().map(|path| self.items(path)).sum(),
+        }
+    }
+
+    // Files declared by `mod` items and include! within the given item of a
+    // file, including those files' own descendants. The inline modules that
+    // the item is inside of are given by name.
+    pub(crate) fn declared_by(&self, file: &Path, inline: &[String], item: &ItemMod) -> Governed {
+        let name = item.ident.to_string();
+        let Some(outline) = self.outlines.get(file) else {
+            return Governed::default();
+        };
+        let mut children = Vec::new();
+        for decl in &outline.decls {
+            let within = if item.content.is_some() {
+                decl.idents.len() > inline.len()
+                    && decl.idents.starts_with(inline)
+                    && decl.idents[inline.len()] == name
+            } else {
+                decl.idents == inline
+                    && matches!(&decl.kind, DeclKind::Mod { name: decl_name, .. } if *decl_name == name)
+            };
+            if within {
+                if let Some(child) = self.resolve_decl(file, decl) {
+                    if !children.contains(&child) {
+                        children.push(child);
+                    }
+                }
+            }
+        }
+        let mut governed = Governed::default();
+        for child in children {
+            let descendants = self.descendants(&child);
+            governed.files += 1 + descendants.files;
+            governed.items += self.items(&child) + descendants.items;
+        }
+        governed
+    }
+
+    fn items(&self, path: &Path) -> usize {
+        self.outlines.get(path).map_or(0, |outline| outline.items)
+    }
+
+    // The file that a declaration in the given file refers to, if the package
+    // has it.
+    fn resolve_decl(&self, file: &Path, decl: &Decl) -> Option<PathBuf> {
+        let candidates = match &decl.kind {
+            DeclKind::Mod {
+                path_attr: Some(path),
+                ..
+            } => vec![self.dir(file, &decl.dirs, true).join(path)],
+            DeclKind::Mod {
+                name,
+                path_attr: None,
+            } => {
+                let dir = self.dir(file, &decl.dirs, false);
+                vec![
+                    dir.join(format!("{}.rs", name)),
+                    dir.join(name).join("mod.rs"),
+                ]
+            }
+            DeclKind::Include(path) => {
+                vec![file.parent().unwrap_or(Path::new("")).join(path)]
+            }
+        };
+        candidates
+            .iter()
+            .map(|candidate| normalize(candidate))
+            .find(|candidate| self.outlines.contains_key(candidate))
+    }
+
+    // Directory containing a file's submodules, or the one that #[path] is
+    // relative to.
+    fn dir(&self, file: &Path, inline: &[String], path_attr: bool) -> PathBuf {
+        let mut dir = file.parent().unwrap_or(Path::new("")).to_owned();
+        let beside_file = self.is_mod_rs(file) || (path_attr && inline.is_empty());
+        if !beside_file {
+            if let Some(stem) = file.file_stem() {
+                dir.push(stem);
+            }
+        }
+        dir.extend(inline);
+        dir
+    }
+
+    // Crate roots and mod.rs files hold their submodules in the directory they
+    // are in, rather than in a directory named after the module.
+    fn is_mod_rs(&self, path: &Path) -> bool {
+        self.roots.contains(path) || path.file_name() == Some("mod.rs".as_ref())
+    }
+}
+
+struct OutlineWalker {
+    // Inline modules that the walker is currently inside of.
+    idents: Vec<String>,
+    dirs: Vec<String>,
+    // Whether the walker is inside of a #[cfg(test)] module.
+    cfg_test: bool,
+    decls: Vec<Decl>,
+}
+
+impl<'ast> Visit<'ast> for OutlineWalker {
+    // #![cfg(test)]
+    fn visit_file(&mut self, node: &'ast syn::File) {
+        self.cfg_test = is_cfg_test(&node.attrs);
+        visit::visit_file(self, node);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast ItemMod) {
+        let path_attr = node.attrs.iter().find_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("path") => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(lit), ..
+                }) => Some(lit.value()),
+                _ => None,
+            },
+            _ => None,
+        });
+        let name = node.ident.to_string();
+        let outer_cfg_test = self.cfg_test;
+        self.cfg_test |= is_cfg_test(&node.attrs);
+        if node.content.is_some() {
+            self.dirs.push(path_attr.unwrap_or_else(|| name.clone()));
+            self.idents.push(name);
+            visit::visit_item_mod(self, node);
+            self.idents.pop();
+            self.dirs.pop();
+        } else {
+            self.push(DeclKind::Mod { name, path_attr });
+        }
+        self.cfg_test = outer_cfg_test;
+    }
+
+    // include!("relative/path.rs")
+    fn visit_item(&mut self, node: &'ast Item) {
+        if let Item::Macro(item) = node {
+            if item.mac.path.is_ident("include") {
+                if let Ok(lit) = item.mac.parse_body::<syn::LitStr>() {
+                    let outer_cfg_test = self.cfg_test;
+                    self.cfg_test |= is_cfg_test(&item.attrs);
+                    self.push(DeclKind::Include(lit.value()));
+                    self.cfg_test = outer_cfg_test;
+                }
+            } else {
+                // cfg_if! { if #[cfg(unix)] { mod unix; } else { mod windows; } }
+                self.scan_tokens(item.mac.tokens.clone());
+            }
+        }
+        visit::visit_item(self, node);
+    }
+
+    // Only items can declare modules.
+    fn visit_block(&mut self, _node: &'ast syn::Block) {}
+    fn visit_impl_item(&mut self, _node: &'ast ImplItem) {}
+    fn visit_trait_item(&mut self, _node: &'ast TraitItem) {}
+}
+
+impl OutlineWalker {
+    fn scan_tokens(&mut self, tokens: TokenStream) {
+        let mut tokens = tokens.into_iter().peekable();
+        while let Some(token) = tokens.next() {
+            let keyword = match token {
+                TokenTree::Ident(ident) => ident,
+                TokenTree::Group(group) => {
+                    self.scan_tokens(group.stream());
+                    continue;
+                }
+                TokenTree::Punct(_) | TokenTree::Literal(_) => continue,
+            };
+            if keyword != "mod" {
+                continue;
+            }
+            let Some(TokenTree::Ident(name)) = tokens.next() else {
+                continue;
+            };
+            if let Some(TokenTree::Punct(semi)) = tokens.peek() {
+                if semi.as_char() == ';' {
+                    let name = name.to_string();
+                    self.push(DeclKind::Mod {
+                        name,
+                        path_attr: None,
+                    });
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, kind: DeclKind) {
+        self.decls.push(Decl {
+            idents: self.idents.clone(),
+            dirs: self.dirs.clone(),
+            cfg_test: self.cfg_test,
+            kind,
+        });
+    }
+}
+
+fn is_cfg_test(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| parse::is_cfg_test(&attr.meta))
+}
+
+// Resolve `.` and `..` without touching the filesystem.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+pub(crate) struct ItemCounter {
+    count: usize,
+}
+
+impl<'ast> Visit<'ast> for ItemCounter {
+    fn visit_item(&mut self, node: &'ast Item) {
+        self.count += 1;
+        visit::visit_item(self, node);
+    }
+
+    fn visit_impl_item(&mut self, node: &'ast ImplItem) {
+        self.count += 1;
+        visit::visit_impl_item(self, node);
+    }
+
+    fn visit_trait_item(&mut self, node: &'ast TraitItem) {
+        self.count += 1;
+        visit::visit_trait_item(self, node);
+    }
+
+    fn visit_foreign_item(&mut self, node: &'ast syn::ForeignItem) {
+        self.count += 1;
+        visit::visit_foreign_item(self, node);
+    }
+}
+
+// Number of items visited by the given function, including nested ones.
+pub(crate) fn count_items(visit: impl FnOnce(&mut ItemCounter)) -> usize {
+    let mut counter = ItemCounter { count: 0 };
+    visit(&mut counter);
+    counter.count
+}
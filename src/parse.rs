@@ -1,61 +1,299 @@
+use crate::lints::{Lint, Namespace};
 use crate::Span;
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use syn::ext::IdentExt as _;
 use syn::parse::{Error, ParseStream, Result};
 use syn::punctuated::Punctuated;
-use syn::{parenthesized, LitStr, Token};
+use syn::{parenthesized, token, Expr, ExprLit, ExprMacro, Ident, Lit, LitStr, Meta, Token};
 
 mod kw {
-    syn::custom_keyword!(allow);
-    syn::custom_keyword!(feature);
+    syn::custom_keyword!(reason);
 }
 
-// #[allow(clippy::lint_id...)]
-pub(crate) fn allow(input: ParseStream) -> Result<Vec<(String, Span)>> {
-    let paths = Punctuated::<syn::Path, Token![,]>::parse_terminated(input)?;
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Level {
+    Allow,
+    Expect,
+    Warn,
+    Deny,
+    Forbid,
+}
+
+impl Level {
+    pub(crate) fn from_ident(ident: &Ident) -> Option<Self> {
+        Level::from_name(&ident.to_string())
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "expect" => Some(Level::Expect),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            "forbid" => Some(Level::Forbid),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_enabled(self) -> bool {
+        match self {
+            Level::Allow | Level::Expect => false,
+            Level::Warn | Level::Deny | Level::Forbid => true,
+        }
+    }
+}
+
+// One lint named by a lint level attribute.
+pub(crate) struct LintAttr {
+    pub level: Level,
+    pub namespace: Namespace,
+    pub lint_id: String,
+    pub span: Span,
+    pub reason: Option<String>,
+}
+
+// A rustc lint allowed alongside Clippy lints so that toolchains which do not
+// know some of those Clippy lints, or know them by a different name, accept
+// the code without a warning.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Shim {
+    UnknownLints,
+    RenamedAndRemovedLints,
+}
+
+impl Shim {
+    pub(crate) fn find<'a>(lints: impl IntoIterator<Item = &'a LintAttr>) -> Option<Self> {
+        lints.into_iter().find_map(|lint| {
+            if lint.namespace != Namespace::Rustc || lint.level.is_enabled() {
+                return None;
+            }
+            match lint.lint_id.as_str() {
+                "unknown_lints" => Some(Shim::UnknownLints),
+                "renamed_and_removed_lints" => Some(Shim::RenamedAndRemovedLints),
+                _ => None,
+            }
+        })
+    }
+}
+
+// #[allow(clippy::lint_id..., rustdoc::lint_id..., lint_id..., reason = "...")]
+pub(crate) fn allow(input: ParseStream, level: Level) -> Result<Vec<LintAttr>> {
+    let (paths, reason) = lint_list(input)?;
 
     let mut lints = Vec::new();
     for path in paths {
-        if path.segments.len() == 2 && path.segments[0].ident == "clippy" {
-            let clippy_ident = &path.segments[0].ident;
-            let lint_ident = &path.segments[1].ident;
-            let span = Span {
-                start: clippy_ident.span().start(),
-                end: lint_ident.span().end(),
-            };
-            lints.push((lint_ident.to_string(), span));
+        if let Some((namespace, lint_id, span)) = lint_path(&path) {
+            let reason = reason.clone();
+            lints.push(LintAttr {
+                level,
+                namespace,
+                lint_id,
+                span,
+                reason,
+            });
         }
     }
 
     Ok(lints)
 }
 
+fn lint_path(path: &syn::Path) -> Option<(Namespace, String, Span)> {
+    let first_ident = &path.segments.first()?.ident;
+    let lint_ident = &path.segments.last()?.ident;
+    let namespace = match path.segments.len() {
+        1 => Namespace::Rustc,
+        2 => Namespace::from_tool(&first_ident.to_string())?,
+        _ => return None,
+    };
+    let span = Span {
+        start: first_ident.span().start(),
+        end: lint_ident.span().end(),
+    };
+    Some((namespace, lint_ident.to_string(), span))
+}
+
 // #[cfg_attr(feature = "cargo-clippy", allow(lint_id...))]
-pub(crate) fn cfg_attr(input: ParseStream) -> Result<Vec<(String, Span)>> {
-    input.parse::<kw::feature>()?;
-    input.parse::<Token![=]>()?;
-    let feature = input.parse::<LitStr>()?;
-    if feature.value() != "cargo-clippy" {
-        let msg = "expected feature = \"cargo-clippy\"";
-        return Err(Error::new(feature.span(), msg));
-    }
+// #[cfg_attr(all(clippy, not(test)), allow(clippy::lint_id...), deny(clippy::lint_id...))]
+//
+// Within cfg_attr, older code refers to Clippy lints by their bare name. Those
+// are taken as Clippy lints if they are a known Clippy lint, and otherwise as
+// a rustc lint like dead_code.
+pub(crate) fn cfg_attr(
+    input: ParseStream,
+    clippy_lints: &Map<&str, &Lint>,
+    features: Option<&Set<String>>,
+) -> Result<Vec<LintAttr>> {
+    let predicate = cfg_predicate(input, features)?;
     input.parse::<Token![,]>()?;
-    input.parse::<kw::allow>()?;
-
-    let list;
-    parenthesized!(list in input);
-    input.parse::<Option<Token![,]>>()?;
-
-    let paths = Punctuated::<syn::Path, Token![,]>::parse_terminated(&list)?;
+    let attrs = Punctuated::<Meta, Token![,]>::parse_terminated(input)?;
 
     let mut lints = Vec::new();
-    for path in paths {
-        if let Some(lint_ident) = path.get_ident() {
-            let span = Span {
-                start: lint_ident.span().start(),
-                end: lint_ident.span().end(),
-            };
-            lints.push((lint_ident.to_string(), span));
+    if predicate == Some(false) {
+        return Ok(lints);
+    }
+    for attr in attrs {
+        let Meta::List(list) = attr else {
+            continue;
+        };
+        if let Some(level) = list.path.get_ident().and_then(Level::from_ident) {
+            let (paths, reason) = list.parse_args_with(lint_list)?;
+            for path in paths {
+                let Some((mut namespace, lint_id, span)) = lint_path(&path) else {
+                    continue;
+                };
+                if namespace == Namespace::Rustc && clippy_lints.contains_key(lint_id.as_str()) {
+                    namespace = Namespace::Clippy;
+                }
+                let reason = reason.clone();
+                lints.push(LintAttr {
+                    level,
+                    namespace,
+                    lint_id,
+                    span,
+                    reason,
+                });
+            }
+        } else if list.path.is_ident("cfg_attr") {
+            let parser = |input: ParseStream| cfg_attr(input, clippy_lints, features);
+            lints.extend(list.parse_args_with(parser)?);
         }
     }
 
     Ok(lints)
 }
+
+// lint_id..., reason = "..."
+fn lint_list(input: ParseStream) -> Result<(Vec<syn::Path>, Option<String>)> {
+    let mut paths = Vec::new();
+    let mut reason = None;
+    while !input.is_empty() {
+        if input.peek(kw::reason) && input.peek2(Token![=]) {
+            input.parse::<kw::reason>()?;
+            input.parse::<Token![=]>()?;
+            reason = Some(input.parse::<LitStr>()?.value());
+        } else {
+            paths.push(input.parse()?);
+        }
+        if input.is_empty() {
+            break;
+        }
+        input.parse::<Token![,]>()?;
+    }
+    Ok((paths, reason))
+}
+
+// Evaluates a cfg predicate as seen by Clippy. Some(true) or Some(false) if the
+// predicate is known to hold or not hold when Clippy runs, and None if it
+// depends on the rest of the build configuration.
+// If the crate's features are known, a feature that the crate does not have
+// can never be enabled.
+fn cfg_predicate(input: ParseStream, features: Option<&Set<String>>) -> Result<Option<bool>> {
+    let ident = input.call(Ident::parse_any)?;
+
+    if input.peek(Token![=]) {
+        input.parse::<Token![=]>()?;
+        let value = input.parse::<LitStr>()?.value();
+        return Ok(if ident != "feature" {
+            None
+        } else if value == "cargo-clippy" {
+            Some(true)
+        } else if features.is_some_and(|features| !features.contains(&value)) {
+            Some(false)
+        } else {
+            None
+        });
+    }
+
+    if !input.peek(token::Paren) {
+        return Ok(if ident == "clippy" || ident == "true" {
+            Some(true)
+        } else if ident == "false" {
+            Some(false)
+        } else {
+            None
+        });
+    }
+
+    let content;
+    parenthesized!(content in input);
+    let mut predicates = Vec::new();
+    while !content.is_empty() {
+        predicates.push(cfg_predicate(&content, features)?);
+        if content.is_empty() {
+            break;
+        }
+        content.parse::<Token![,]>()?;
+    }
+    if ident == "all" {
+        if predicates.contains(&Some(false)) {
+            Ok(Some(false))
+        } else if predicates.iter().all(|predicate| *predicate == Some(true)) {
+            Ok(Some(true))
+        } else {
+            Ok(None)
+        }
+    } else if ident == "any" {
+        if predicates.contains(&Some(true)) {
+            Ok(Some(true))
+        } else if predicates.iter().all(|predicate| *predicate == Some(false)) {
+            Ok(Some(false))
+        } else {
+            Ok(None)
+        }
+    } else if ident == "not" && predicates.len() == 1 {
+        Ok(predicates[0].map(|predicate| !predicate))
+    } else {
+        Err(Error::new(ident.span(), "unsupported cfg predicate"))
+    }
+}
+
+// #[cfg(test)], or a predicate that can only hold under test, like
+// #[cfg(all(test, feature = "..."))].
+pub(crate) fn is_cfg_test(meta: &Meta) -> bool {
+    let Meta::List(list) = meta else {
+        return false;
+    };
+    list.path.is_ident("cfg")
+        && list
+            .parse_args::<Meta>()
+            .is_ok_and(|predicate| requires_test(&predicate))
+}
+
+fn requires_test(predicate: &Meta) -> bool {
+    let Meta::List(list) = predicate else {
+        return predicate.path().is_ident("test");
+    };
+    let Ok(predicates) = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+    else {
+        return false;
+    };
+    if list.path.is_ident("all") {
+        predicates.iter().any(requires_test)
+    } else if list.path.is_ident("any") {
+        !predicates.is_empty() && predicates.iter().all(requires_test)
+    } else {
+        false
+    }
+}
+
+// #![doc = include_str!("../README.md")]
+pub(crate) fn doc_include(meta: &Meta) -> Option<String> {
+    let Meta::NameValue(meta) = meta else {
+        return None;
+    };
+    if !meta.path.is_ident("doc") {
+        return None;
+    }
+    let Expr::Macro(ExprMacro { mac, .. }) = &meta.value else {
+        return None;
+    };
+    if !mac.path.is_ident("include_str") {
+        return None;
+    }
+    match mac.parse_body::<Expr>().ok()? {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Some(lit.value()),
+        _ => None,
+    }
+}
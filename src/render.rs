@@ -1,25 +1,68 @@
-use crate::{Locations, SourceFile, Span};
+use crate::lints::Namespace;
+use crate::{Locations, SourceFile, Span, Target};
 use proc_macro2::LineColumn;
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
 use std::path::Component;
 use std::str;
 
-pub(crate) fn render(lint_id: &str, findings: &Map<SourceFile, Locations>) -> String {
+pub(crate) fn render(
+    namespace: Namespace,
+    lint_id: &str,
+    findings: &Map<SourceFile, Locations>,
+    tuned: usize,
+) -> String {
+    let lint_path = namespace.lint_path(lint_id);
+    let stylesheet = if namespace == Namespace::Clippy {
+        "style.css"
+    } else {
+        "../style.css"
+    };
+
     let mut html = String::new();
     html.push_str("<!DOCTYPE html>\n");
     html.push_str("<html lang=\"en\">\n");
     html.push_str("<head>\n");
     html.push_str("  <meta charset=\"utf-8\">\n");
-    html.push_str("  <link rel=\"stylesheet\" href=\"style.css\">\n");
+    html.push_str("  <link rel=\"stylesheet\" href=\"");
+    html.push_str(stylesheet);
+    html.push_str("\">\n");
     html.push_str("</head>\n");
     html.push_str("<body>\n");
+    if tuned > 0 {
+        html.push_str("  <p class=\"summary\">");
+        html.push_str(&tuned.to_string());
+        html.push_str(if tuned == 1 { " crate" } else { " crates" });
+        html.push_str(" configure this lint in clippy.toml instead of suppressing it.</p>\n");
+    }
+
+    // links that show only the suppressions applying to one kind of item
+    let mut target_counts = Map::new();
+    for locations in findings.values() {
+        for span in locations.spans() {
+            if let Some(target) = locations.targets.get(span) {
+                *target_counts.entry(target.name()).or_insert(0) += 1;
+            }
+        }
+    }
+    html.push_str("  <p class=\"filters\"><a href=\"#\">all</a>");
+    for target in Target::ALL {
+        if let Some(count) = target_counts.get(target.name()) {
+            html.push_str(" &middot; <a href=\"#target=");
+            html.push_str(target.name());
+            html.push_str("\">");
+            html.push_str(target.title());
+            html.push_str("&nbsp;(");
+            html.push_str(&count.to_string());
+            html.push_str(")</a>");
+        }
+    }
+    html.push_str("</p>\n");
     html.push_str("  <ul class=\"results-container\">\n");
 
     // write files containing a local suppression first
     let mut local_anchor = Some("local");
     for (source_file, locations) in findings {
         if !locations.local.is_empty() {
-            let contents = &locations.contents;
             let spans = locations
                 .global
                 .iter()
@@ -27,7 +70,14 @@ pub(crate) fn render(lint_id: &str, findings: &Map<SourceFile, Locations>) -> St
                 .copied()
                 .collect();
             let anchor = local_anchor.take();
-            render_file(&mut html, lint_id, source_file, contents, &spans, anchor);
+            render_file(
+                &mut html,
+                &lint_path,
+                source_file,
+                locations,
+                &spans,
+                anchor,
+            );
         }
     }
 
@@ -35,14 +85,61 @@ pub(crate) fn render(lint_id: &str, findings: &Map<SourceFile, Locations>) -> St
     let mut global_anchor = Some("global");
     for (source_file, locations) in findings {
         if locations.local.is_empty() && !locations.global.is_empty() {
-            let contents = &locations.contents;
             let spans = locations.global.iter().copied().collect();
             let anchor = global_anchor.take();
-            render_file(&mut html, lint_id, source_file, contents, &spans, anchor);
+            render_file(
+                &mut html,
+                &lint_path,
+                source_file,
+                locations,
+                &spans,
+                anchor,
+            );
         }
     }
 
+    // then each other kind of suppression in a section of its own
+    let mut section = |anchor, spans: fn(&Locations) -> &Vec<Span>| {
+        let mut anchor = Some(anchor);
+        for (source_file, locations) in findings {
+            if !spans(locations).is_empty() {
+                let spans = spans(locations).iter().copied().collect();
+                render_file(
+                    &mut html,
+                    &lint_path,
+                    source_file,
+                    locations,
+                    &spans,
+                    anchor.take(),
+                );
+            }
+        }
+    };
+    // lints configured in Cargo.toml
+    section("manifest", |loc| &loc.manifest);
+    // flags passed to rustc or clippy-driver by configuration or CI
+    section("command-line", |loc| &loc.command_line);
+    // attributes inside of macro_rules bodies and macro invocations
+    section("macro", |loc| &loc.in_macro);
+    // attributes emitted by proc macros into their users' code
+    section("proc-macro", |loc| &loc.proc_macro);
+    // code blocks in documentation, which rustdoc compiles as doctests
+    section("doc-example", |loc| &loc.doc_example);
+    // expect, which is reported separately from allow
+    section("expect", |loc| &loc.expect);
+
     html.push_str("  </ul>\n");
+    html.push_str("  <script>\n");
+    html.push_str("    function filter() {\n");
+    html.push_str("      var m = location.hash.match(/^#target=(.*)$/);\n");
+    html.push_str("      document.querySelectorAll('.result').forEach(function (result) {\n");
+    html.push_str("        var targets = result.dataset.targets.split(' ');\n");
+    html.push_str("        result.hidden = m !== null && targets.indexOf(m[1]) < 0;\n");
+    html.push_str("      });\n");
+    html.push_str("    }\n");
+    html.push_str("    window.addEventListener('hashchange', filter);\n");
+    html.push_str("    filter();\n");
+    html.push_str("  </script>\n");
     html.push_str("</body>\n");
     html.push_str("</html>\n");
     html
@@ -50,18 +147,32 @@ pub(crate) fn render(lint_id: &str, findings: &Map<SourceFile, Locations>) -> St
 
 fn render_file(
     html: &mut String,
-    lint_id: &str,
+    lint_path: &str,
     source_file: &SourceFile,
-    contents: &str,
+    locations: &Locations,
     spans: &Set<Span>,
     anchor: Option<&str>,
 ) {
+    let contents = &locations.contents;
+    let justifications = &locations.justifications;
+    let targets = spans
+        .iter()
+        .filter_map(|span| locations.targets.get(span))
+        .map(|target| target.name())
+        .collect::<Set<_>>();
+    let (files, items) = spans
+        .iter()
+        .filter_map(|span| locations.governed.get(span))
+        .fold((0, 0), |(files, items), governed| {
+            (files + governed.files, items + governed.items)
+        });
+
     let url = format!(
-        "https://docs.rs/crate/{}/{}/source/{}#:~:text=clippy%3a%3a{}",
+        "https://docs.rs/crate/{}/{}/source/{}#:~:text={}",
         source_file.krate,
         source_file.version,
         source_file.relative_path.display(),
-        lint_id,
+        lint_path.replace("::", "%3a%3a"),
     );
 
     html.push_str("    <li class=\"result\"");
@@ -70,13 +181,17 @@ fn render_file(
         html.push_str(anchor);
         html.push('"');
     }
-    html.push_str(">\n");
+    html.push_str(" data-targets=\"");
+    html.push_str(&Vec::from_iter(targets).join(" "));
+    html.push_str("\">\n");
     html.push_str("      <div class=\"result-title\">\n");
     html.push_str("        <div class=\"result-file-info\">\n");
     html.push_str("          <div class=\"result-file\"><span class=\"result-crate\">");
     html.push_str(&source_file.krate.to_string());
     html.push_str("&nbsp;");
     html.push_str(&source_file.version.to_string());
+    html.push_str("</span><span class=\"result-kind\">");
+    html.push_str(&source_file.target_kind.to_string());
     html.push_str("</span><a href=\"");
     html.push_str(&url);
     html.push_str("\"><span class=\"result-file-directory\">/");
@@ -96,6 +211,15 @@ fn render_file(
         }
     }
     html.push_str("</span></a></div>\n");
+    if files + items > 0 {
+        html.push_str("          <div class=\"result-governs\">applies to ");
+        if files > 0 {
+            html.push_str(&plural(files, "file"));
+            html.push_str(", ");
+        }
+        html.push_str(&plural(items, "item"));
+        html.push_str("</div>\n");
+    }
     html.push_str("        </div>\n");
     html.push_str("      </div>\n");
     html.push_str("      <div class=\"result-body\" onclick=\"window.open('");
@@ -105,6 +229,11 @@ fn render_file(
     html.push_str("        <div class=\"result-details\">\n");
     html.push_str("          <table class=\"highlight-table\">\n");
 
+    let justifications: Vec<&String> = spans
+        .iter()
+        .filter_map(|span| justifications.get(span))
+        .collect();
+
     let lines: Vec<_> = contents.lines().collect();
     let mut show = vec![false; lines.len()];
     for span in spans {
@@ -193,11 +322,27 @@ fn render_file(
     }
 
     html.push_str("          </table>\n");
+
+    if !justifications.is_empty() {
+        html.push_str("          <ul class=\"justifications\">\n");
+        for justification in justifications {
+            html.push_str("            <li>");
+            for ch in justification.chars() {
+                html_escape(html, ch);
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("          </ul>\n");
+    }
     html.push_str("        </div>\n");
     html.push_str("      </div>\n");
     html.push_str("    </li>\n");
 }
 
+fn plural(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
 fn html_escape(html: &mut String, ch: char) {
     match ch {
         '&' => html.push_str("&amp;"),
@@ -0,0 +1,57 @@
+use crate::dbdump::Date;
+use clap::ValueEnum;
+use semver::Version;
+
+// Which versions of a crate may be chosen as the one to scan. The newest of
+// those that remain is scanned, or all of them in history mode.
+#[derive(ValueEnum, Copy, Clone, Default, PartialEq, Debug)]
+pub(crate) enum Policy {
+    /// Any version; the newest is the greatest by semver.
+    #[default]
+    Max,
+    /// Versions that are not yanked, and not a prerelease unless the crate has
+    /// no others.
+    Stable,
+    /// Versions that are not yanked.
+    NonYanked,
+}
+
+impl Policy {
+    // Whether the policy needs yanked status from the db dump or index.
+    pub(crate) fn needs_yanked(self) -> bool {
+        self == Policy::NonYanked
+    }
+}
+
+// What the db dump and index say about one version, if they have it.
+#[derive(Copy, Clone, Default)]
+pub(crate) struct VersionInfo {
+    pub created_at: Option<Date>,
+    pub yanked: Option<bool>,
+}
+
+// The versions that the policy allows, published on or before the given
+// date if any. Versions of unknown status are kept unless their publish date
+// is needed. Yanked status is only known with the db dump or index.
+pub(crate) fn eligible<'a>(
+    versions: impl IntoIterator<Item = (&'a Version, VersionInfo)>,
+    policy: Policy,
+    as_of: Option<Date>,
+) -> Vec<&'a Version> {
+    let mut eligible = Vec::new();
+    for (version, info) in versions {
+        if let Some(as_of) = as_of {
+            if info.created_at.is_none_or(|created_at| created_at > as_of) {
+                continue;
+            }
+        }
+        if policy != Policy::Max && info.yanked == Some(true) {
+            continue;
+        }
+        eligible.push(version);
+    }
+    if policy == Policy::Stable && eligible.iter().any(|version| version.pre.is_empty()) {
+        eligible.retain(|version| version.pre.is_empty());
+    }
+    eligible
+}
@@ -0,0 +1,154 @@
+use clap::ValueEnum;
+use std::ffi::OsStr;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+// The kind of Cargo target that a source file is compiled into.
+#[derive(ValueEnum, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+pub(crate) enum TargetKind {
+    Lib,
+    Bin,
+    Test,
+    Example,
+    Bench,
+    BuildScript,
+    // Not part of any target, like a fuzz or xtask directory.
+    Other,
+}
+
+impl Display for TargetKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Test => "test",
+            TargetKind::Example => "example",
+            TargetKind::Bench => "bench",
+            TargetKind::BuildScript => "build script",
+            TargetKind::Other => "other",
+        })
+    }
+}
+
+pub(crate) struct Targets {
+    // Root source file of each target whose path is given in the manifest.
+    explicit: Vec<(TargetKind, PathBuf)>,
+    // Kind of the files in src/ outside of src/bin/.
+    src: TargetKind,
+    // Whether build.rs is compiled, unless the manifest names another file.
+    build_script: bool,
+}
+
+impl Targets {
+    pub(crate) fn new(
+        explicit: Vec<(TargetKind, PathBuf)>,
+        has_lib_rs: bool,
+        build_script: bool,
+    ) -> Self {
+        let has_lib = has_lib_rs || explicit.iter().any(|(kind, _)| *kind == TargetKind::Lib);
+        Targets {
+            explicit,
+            src: if has_lib {
+                TargetKind::Lib
+            } else {
+                TargetKind::Bin
+            },
+            build_script,
+        }
+    }
+
+    // The [lints] table of the manifest applies to every target in the
+    // package. Its findings are attributed to the main one.
+    pub(crate) fn primary(&self) -> TargetKind {
+        self.src
+    }
+
+    // Root source file of every target, among the given files of the package.
+    pub(crate) fn roots<'a>(&self, paths: impl IntoIterator<Item = &'a PathBuf>) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        for (_kind, root) in &self.explicit {
+            roots.push(root.clone());
+        }
+        for path in paths {
+            if path == Path::new("build.rs") && !self.build_script {
+                continue;
+            }
+            if is_conventional_root(path) && !roots.contains(path) {
+                roots.push(path.clone());
+            }
+        }
+        roots
+    }
+
+    pub(crate) fn kind(&self, path: &Path) -> TargetKind {
+        if let Some((kind, _root)) = self.explicit.iter().find(|(_kind, root)| root == path) {
+            return *kind;
+        }
+
+        if path == Path::new("build.rs") {
+            return if self.build_script {
+                TargetKind::BuildScript
+            } else {
+                TargetKind::Other
+            };
+        }
+        for (dir, kind) in [
+            ("src/bin", TargetKind::Bin),
+            ("tests", TargetKind::Test),
+            ("examples", TargetKind::Example),
+            ("benches", TargetKind::Bench),
+        ] {
+            if path.starts_with(dir) {
+                return kind;
+            }
+        }
+
+        // Modules of a target whose root is somewhere unconventional, like
+        // [[bin]] path = "cli/main.rs".
+        let mut nearest = None;
+        for (kind, root) in &self.explicit {
+            let Some(dir) = root.parent() else {
+                continue;
+            };
+            if dir == Path::new("") || dir == Path::new("src") || !path.starts_with(dir) {
+                continue;
+            }
+            let depth = dir.components().count();
+            if nearest.is_none_or(|(_kind, nearest_depth)| depth > nearest_depth) {
+                nearest = Some((*kind, depth));
+            }
+        }
+        if let Some((kind, _depth)) = nearest {
+            return kind;
+        }
+
+        if path.starts_with("src") {
+            self.src
+        } else {
+            TargetKind::Other
+        }
+    }
+}
+
+// Whether the path is where Cargo looks for the root of a target when the
+// manifest does not say.
+pub(crate) fn is_conventional_root(path: &Path) -> bool {
+    if path == Path::new("src/lib.rs")
+        || path == Path::new("src/main.rs")
+        || path == Path::new("build.rs")
+    {
+        return true;
+    }
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    // src/bin/name.rs or src/bin/name/main.rs, and likewise in tests,
+    // examples, and benches.
+    ["src/bin", "tests", "examples", "benches"]
+        .into_iter()
+        .any(|dir| {
+            parent == Path::new(dir)
+                || (parent.parent() == Some(Path::new(dir))
+                    && path.file_name() == Some(OsStr::new("main.rs")))
+        })
+}
@@ -1,13 +1,120 @@
+use crate::dbdump::{Date, Popularity, Quarter};
+use crate::history::{self, QuarterRow};
+use crate::index::{self, IndexEntry};
+use crate::lints::{Lint, LintGroup, LintLevel, Namespace};
+use crate::lockfile;
+use crate::modules::{Governed, ModuleTree, Outline, Source};
 use crate::name::Crate;
-use crate::{AttrVisitor, SourceFile};
-use parking_lot::Mutex;
+use crate::parse::{self, Level, Shim};
+use crate::select::{self, Policy, VersionInfo};
+use crate::target::{TargetKind, Targets};
+use crate::weight::{self, SortBy, Weight};
+use crate::{
+    config, doc, flags, generated, manifest, AttrVisitor, Collected, Context, CrateScan, Enabled,
+    FileStatus, Findings, Locations, Opt, SourceFile, Span, Target,
+};
+use clap::Parser as _;
+use proc_macro2::{LineColumn, TokenStream};
 use quote::quote;
 use semver::Version;
-use std::collections::BTreeMap as Map;
-use std::path::PathBuf;
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use syn::visit::Visit;
-use syn::File;
+use syn::parse::{ParseStream, Parser};
+
+fn visit(input: &TokenStream) -> (Findings, Enabled) {
+    visit_crate(input, false)
+}
+
+fn visit_crate(input: &TokenStream, proc_macro: bool) -> (Findings, Enabled) {
+    visit_source(&input.to_string(), proc_macro)
+}
+
+fn visit_source(contents: &str, proc_macro: bool) -> (Findings, Enabled) {
+    let (mut findings, mut enabled) = visit_namespaces(contents, proc_macro);
+    (
+        findings.remove(&Namespace::Clippy).unwrap_or_default(),
+        enabled.remove(&Namespace::Clippy).unwrap_or_default(),
+    )
+}
+
+fn visit_namespaces(
+    contents: &str,
+    proc_macro: bool,
+) -> (Map<Namespace, Findings>, Map<Namespace, Enabled>) {
+    let (status, findings, enabled) = scan(contents, proc_macro);
+    assert_eq!(status, FileStatus::Parsed);
+    (findings, enabled)
+}
+
+fn scan(
+    contents: &str,
+    proc_macro: bool,
+) -> (
+    FileStatus,
+    Map<Namespace, Findings>,
+    Map<Namespace, Enabled>,
+) {
+    let source = Source::parse(contents);
+    scan_with("src/lib.rs", contents, proc_macro, |visitor| {
+        visitor.scan_file(&source)
+    })
+}
+
+// Runs the given scan of a file of the given path in a crate named "test".
+fn scan_with(
+    relative_path: &str,
+    contents: &str,
+    proc_macro: bool,
+    scan: impl FnOnce(&mut AttrVisitor) -> FileStatus,
+) -> (
+    FileStatus,
+    Map<Namespace, Findings>,
+    Map<Namespace, Enabled>,
+) {
+    let lints_vec =
+        [("jkl", None), ("needless_return", Some("old_return"))].map(|(id, former_id)| Lint {
+            id: id.to_owned(),
+            group: LintGroup::Style,
+            level: LintLevel::Warn,
+            former_ids: former_id.into_iter().map(str::to_owned).collect(),
+        });
+    let mut lints = Map::new();
+    for namespace in Namespace::ALL {
+        lints.insert(namespace, Map::new());
+    }
+    for lint in &lints_vec {
+        let clippy_lints = lints.get_mut(&Namespace::Clippy).unwrap();
+        clippy_lints.insert(lint.id.as_str(), lint);
+        for former_id in &lint.former_ids {
+            clippy_lints.insert(former_id.as_str(), lint);
+        }
+    }
+
+    let collected = Collected::default();
+    let modules = ModuleTree::default();
+    let crate_scan = CrateScan {
+        collected: &collected,
+        lints: &lints,
+        proc_macro,
+        features: None,
+        modules: &modules,
+    };
+    let source_file = SourceFile {
+        krate: Crate::new("test".to_owned()),
+        version: Version::new(0, 0, 0),
+        relative_path: PathBuf::from(relative_path),
+        target_kind: TargetKind::Lib,
+    };
+    let mut visitor = crate_scan.visitor(&source_file, contents.to_owned(), Context::default());
+
+    let status = scan(&mut visitor);
+    (
+        status,
+        collected.findings.into_inner(),
+        collected.enabled.into_inner(),
+    )
+}
 
 #[test]
 fn test_attr_visitor() {
@@ -20,22 +127,1186 @@ fn test_attr_visitor() {
         }
     };
 
-    let findings = Mutex::new(Map::new());
-    let mut visitor = AttrVisitor {
-        source_file: &SourceFile {
-            krate: Crate::new("test".to_owned()),
-            version: Version::new(0, 0, 0),
-            relative_path: PathBuf::from("src/lib.rs"),
-        },
-        contents: Arc::new(input.to_string()),
-        findings: &findings,
-        lints: &Map::new(),
+    let (findings, _enabled) = visit(&input);
+    assert_eq!(findings["asdf"].len(), 1);
+    assert_eq!(findings["jkl"].len(), 1);
+}
+
+#[test]
+fn test_expect() {
+    let input = quote! {
+        #![expect(clippy::asdf)]
+
+        #[expect(clippy::asdf, clippy::jkl)]
+        fn main() {}
+    };
+
+    let (findings, _enabled) = visit(&input);
+    let locations = &findings["asdf"].values().next().unwrap();
+    assert_eq!(locations.expect.len(), 2);
+    assert!(locations.global.is_empty() && locations.local.is_empty());
+    assert_eq!(findings["jkl"].len(), 1);
+}
+
+#[test]
+fn test_enabled() {
+    let input = quote! {
+        #![warn(clippy::pedantic)]
+        #![deny(clippy::asdf)]
+        #![cfg_attr(feature = "cargo-clippy", forbid(jkl))]
+
+        #[allow(clippy::asdf)]
+        fn main() {}
     };
 
-    let file: File = syn::parse_str(&visitor.contents).unwrap();
-    visitor.visit_file(&file);
+    let (findings, enabled) = visit(&input);
+    assert_eq!(findings["asdf"].len(), 1);
+    assert_eq!(enabled["pedantic"].len(), 1);
+    assert_eq!(enabled["asdf"].len(), 1);
+    assert_eq!(enabled["jkl"].len(), 1);
+    assert!(!findings.contains_key("pedantic"));
+}
+
+#[test]
+fn test_cfg_attr_predicates() {
+    let input = quote! {
+        #![cfg_attr(clippy, allow(clippy::asdf))]
+        #![cfg_attr(all(clippy, not(test)), allow(clippy::jkl), expect(clippy::qwer))]
+        #![cfg_attr(any(test, feature = "std"), cfg_attr(clippy, allow(clippy::uiop)))]
+        #![cfg_attr(not(clippy), allow(clippy::zxcv))]
+        #![cfg_attr(all(feature = "cargo-clippy", not(clippy)), allow(clippy::bnm))]
+    };
 
-    let findings = findings.into_inner();
+    let (findings, _enabled) = visit(&input);
     assert_eq!(findings["asdf"].len(), 1);
     assert_eq!(findings["jkl"].len(), 1);
+    assert_eq!(findings["qwer"].len(), 1);
+    assert_eq!(findings["uiop"].len(), 1);
+    assert!(!findings.contains_key("zxcv"));
+    assert!(!findings.contains_key("bnm"));
+}
+
+#[test]
+fn test_cfg_attr_lint_paths() {
+    let input = quote! {
+        #[cfg_attr(feature = "cargo-clippy", allow(clippy::asdf, needless_return, dead_code))]
+        fn main() {}
+    };
+
+    let (findings, _enabled) = visit_namespaces(&input.to_string(), false);
+    assert_eq!(findings[&Namespace::Clippy]["asdf"].len(), 1);
+    assert_eq!(findings[&Namespace::Clippy]["needless_return"].len(), 1);
+    assert!(!findings[&Namespace::Clippy].contains_key("dead_code"));
+    assert_eq!(findings[&Namespace::Rustc]["dead_code"].len(), 1);
+}
+
+#[test]
+fn test_namespaces() {
+    let input = quote! {
+        #![allow(unused, rustdoc::broken_intra_doc_links)]
+        #![deny(unsafe_code, rustdoc::missing_crate_level_docs)]
+
+        #[allow(dead_code, clippy::asdf, other_tool::lint)]
+        fn main() {}
+    };
+
+    let (findings, enabled) = visit_namespaces(&input.to_string(), false);
+    assert_eq!(findings[&Namespace::Clippy]["asdf"].len(), 1);
+    assert_eq!(findings[&Namespace::Rustc]["unused"].len(), 1);
+    assert_eq!(findings[&Namespace::Rustc]["dead_code"].len(), 1);
+    assert_eq!(findings[&Namespace::Rustc].len(), 2);
+    let rustdoc = &findings[&Namespace::Rustdoc];
+    assert_eq!(rustdoc["broken_intra_doc_links"].len(), 1);
+    assert!(enabled[&Namespace::Rustc].contains_key("unsafe_code"));
+    assert!(enabled[&Namespace::Rustdoc].contains_key("missing_crate_level_docs"));
+}
+
+#[test]
+fn test_manifest_lints() {
+    let contents = r#"
+[package]
+name = "test"
+
+[lib]
+proc-macro = true
+
+[lints.clippy]
+pedantic = { level = "warn", priority = -1 }
+module_name_repetitions = "allow"
+
+[lints.rust]
+unsafe_code = "forbid"
+
+[workspace.lints.clippy]
+asdf = { level = "allow" }
+"#;
+
+    let manifest = manifest::parse(contents).unwrap();
+    assert!(manifest.proc_macro);
+    let lints: Vec<_> = manifest
+        .lints
+        .iter()
+        .map(|lint| {
+            let lint_id = lint.lint_id.as_str();
+            (lint.level, lint.namespace, lint_id, lint.span.start.line)
+        })
+        .collect();
+    assert_eq!(
+        lints,
+        [
+            (
+                Level::Allow,
+                Namespace::Clippy,
+                "module_name_repetitions",
+                10
+            ),
+            (Level::Warn, Namespace::Clippy, "pedantic", 9),
+            (Level::Forbid, Namespace::Rustc, "unsafe_code", 13),
+            (Level::Allow, Namespace::Clippy, "asdf", 16),
+        ],
+    );
+}
+
+#[test]
+fn test_clippy_toml() {
+    let contents = r#"
+too-many-arguments-threshold = 10
+type_complexity_threshold = 500
+doc-valid-idents = ["GitHub", ".."]
+"#;
+
+    let other = "too_many_arguments_threshold = 12\n";
+
+    let test = Crate::new("test".to_owned());
+    let mut configs = Map::new();
+    config::tally(&mut configs, &test, config::options(contents).unwrap());
+    let other_crate = Crate::new("other".to_owned());
+    config::tally(&mut configs, &other_crate, config::options(other).unwrap());
+
+    assert_eq!(configs["too-many-arguments-threshold"].len(), 2);
+    assert_eq!(configs["too-many-arguments-threshold"][&test], "10");
+    assert_eq!(configs["too-many-arguments-threshold"][&other_crate], "12");
+    assert_eq!(configs["type-complexity-threshold"].len(), 1);
+    assert_eq!(
+        config::crates_tuning("too_many_arguments", &configs).len(),
+        2
+    );
+    assert_eq!(config::crates_tuning("doc_markdown", &configs).len(), 1);
+    assert!(config::crates_tuning("too_many_lines", &configs).is_empty());
+}
+
+#[test]
+fn test_macro_tokens() {
+    let input: TokenStream = r"
+        macro_rules! m {
+            ($name:ident) => {
+                #[allow(clippy::asdf)]
+                fn $name() {
+                    #![cfg_attr(clippy, allow(clippy::jkl))]
+                }
+            };
+        }
+
+        fn f() -> TokenStream {
+            quote! {
+                #[allow(clippy::asdf)]
+                impl #impl_generics Trait for #ident #ty_generics {
+                    #(#fields)*
+                }
+            }
+        }
+    "
+    .parse()
+    .unwrap();
+
+    let (findings, _enabled) = visit(&input);
+    let locations = &findings["asdf"].values().next().unwrap();
+    assert_eq!(locations.in_macro.len(), 2);
+    assert!(locations.local.is_empty());
+    assert_eq!(findings["jkl"].len(), 1);
+}
+
+#[test]
+fn test_proc_macro() {
+    let input = quote! {
+        #[proc_macro_derive(Trait)]
+        pub fn derive(input: TokenStream) -> TokenStream {
+            let expanded = quote::quote! {
+                #[allow(clippy::asdf)]
+                impl Trait for T {}
+            };
+            expanded.into()
+        }
+    };
+
+    let (findings, _enabled) = visit_crate(&input, true);
+    let locations = &findings["asdf"].values().next().unwrap();
+    assert_eq!(locations.proc_macro.len(), 1);
+    assert!(locations.in_macro.is_empty());
+
+    let (findings, _enabled) = visit_crate(&input, false);
+    let locations = &findings["asdf"].values().next().unwrap();
+    assert!(locations.proc_macro.is_empty());
+    assert_eq!(locations.in_macro.len(), 1);
+
+    // Limiting the rendered occurrences keeps some of each kind.
+    let contents = Arc::new(String::new());
+    let span = |line| Span {
+        start: LineColumn { line, column: 0 },
+        end: LineColumn { line, column: 1 },
+    };
+    let mut locations = Locations::new(&contents);
+    locations.local.extend((1..7).map(span));
+    locations.proc_macro.push(span(7));
+    locations.expect.push(span(8));
+    locations.truncate(5);
+    assert_eq!(locations.local.len(), 3);
+    assert_eq!(locations.proc_macro.len(), 1);
+    assert_eq!(locations.expect.len(), 1);
+}
+
+#[test]
+fn test_lint_groups() {
+    assert_eq!(LintGroup::from_name("pedantic"), Some(LintGroup::Pedantic));
+    assert_eq!(LintGroup::from_name("all"), Some(LintGroup::All));
+    assert_eq!(LintGroup::from_name("unknown"), None);
+    assert_eq!(LintGroup::from_name("needless_return"), None);
+    assert!(LintGroup::All.contains(LintGroup::Style));
+    assert!(!LintGroup::All.contains(LintGroup::Pedantic));
+    assert!(LintGroup::Pedantic.contains(LintGroup::Pedantic));
+    assert!(!LintGroup::Pedantic.contains(LintGroup::Nursery));
+}
+
+#[test]
+fn test_justifications() {
+    let contents = r#"
+        #![allow(clippy::asdf, reason = "false positive")]
+
+        // Mirrors the upstream API.
+        #[allow(clippy::jkl)]
+        fn f() {}
+
+        #[allow(clippy::jkl)] // Generated.
+        fn g() {}
+
+        /// Documentation.
+        #[allow(clippy::jkl)]
+        fn h() {}
+    "#;
+
+    let (findings, _enabled) = visit_source(contents, false);
+    let asdf = &findings["asdf"].values().next().unwrap();
+    assert_eq!(asdf.global.len(), 1);
+    let justifications: Vec<_> = asdf.justifications.values().collect();
+    assert_eq!(justifications, ["false positive"]);
+    let jkl = &findings["jkl"].values().next().unwrap();
+    assert_eq!(jkl.local.len(), 3);
+    let justifications: Vec<_> = jkl.justifications.values().collect();
+    assert_eq!(justifications, ["Mirrors the upstream API.", "Generated."]);
+
+    // Comments in Cargo.toml and in CI configuration start with `#`.
+    let manifest = r#"
+[lints.clippy]
+# Too many false positives.
+asdf = "allow"
+jkl = { level = "allow", priority = 1 } # Matches serde's naming.
+"#;
+    let (_status, findings, _enabled) = scan_with("Cargo.toml", manifest, false, |visitor| {
+        visitor.record_manifest(manifest::parse(manifest).unwrap().lints);
+        FileStatus::Parsed
+    });
+    let findings = &findings[&Namespace::Clippy];
+    let justification = |lint_id: &str| {
+        let locations = findings[lint_id].values().next().unwrap();
+        locations.justifications.values().next().cloned()
+    };
+    assert_eq!(justification("asdf").unwrap(), "Too many false positives.");
+    assert_eq!(justification("jkl").unwrap(), "Matches serde's naming.");
+
+    let workflow = r#"
+    steps:
+      # Pedantic lints are only a suggestion.
+      - run: cargo clippy -- -W clippy::pedantic -A clippy::asdf
+      - run: cargo clippy -- "-Aclippy::jkl" "--cfg=x#y" # Issue #12.
+"#;
+    let rustc_lints = Map::new();
+    let flags = flags::parse(workflow, &rustc_lints);
+    let (_status, findings, _enabled) =
+        scan_with(".github/workflows/ci.yml", workflow, false, |visitor| {
+            visitor.record_flags(flags);
+            FileStatus::Parsed
+        });
+    let findings = &findings[&Namespace::Clippy];
+    let justification = |lint_id: &str| {
+        let locations = findings[lint_id].values().next().unwrap();
+        locations.justifications.values().next().cloned()
+    };
+    assert_eq!(
+        justification("asdf").unwrap(),
+        "Pedantic lints are only a suggestion.",
+    );
+    assert_eq!(justification("jkl").unwrap(), "Issue #12.");
+}
+
+#[test]
+fn test_bundled_lint_lists() {
+    let rustc = Namespace::Rustc.lint_list().unwrap();
+    let level = |id: &str| rustc.iter().find(|lint| lint.id == id).unwrap().level;
+    assert_eq!(level("dead_code"), LintLevel::Warn);
+    assert_eq!(level("unsafe_code"), LintLevel::Allow);
+    assert_eq!(level("unused"), LintLevel::None);
+    assert_eq!(level("warnings"), LintLevel::None);
+    let warnings = rustc.iter().filter(|lint| lint.id == "warnings").count();
+    assert_eq!(warnings, 1);
+
+    let rustdoc = Namespace::Rustdoc.lint_list().unwrap();
+    assert!(rustdoc
+        .iter()
+        .any(|lint| lint.id == "broken_intra_doc_links"));
+}
+
+#[test]
+fn test_fallback() {
+    let contents = r"
+#![allow(clippy::asdf)]
+
+fn main() {
+    #![allow(clippy::qwer)]
+    let x = do yeet 1;
+    if !cond(x) {}
+}
+
+#[allow(clippy::jkl)]
+struct S;
+
+m! {
+    #[allow(clippy::needless_return)]
+}
+";
+
+    let (status, findings, _enabled) = scan(contents, false);
+    assert_eq!(status, FileStatus::Fallback);
+    let findings = &findings[&Namespace::Clippy];
+    let asdf = &findings["asdf"].first_key_value().unwrap().1;
+    assert_eq!(asdf.global.len(), 1);
+    assert_eq!(asdf.targets.values().next(), Some(&Target::CrateRoot));
+    assert_eq!(asdf.governed.values().next().unwrap().files, 1);
+    // Only inner attributes at the top level of the file apply to the file.
+    let qwer = &findings["qwer"].first_key_value().unwrap().1;
+    assert_eq!(qwer.global.len(), 1);
+    assert_eq!(qwer.targets.values().next(), Some(&Target::Other));
+    assert_eq!(qwer.governed.values().next(), Some(&Governed::default()));
+    let jkl = &findings["jkl"].first_key_value().unwrap().1;
+    assert_eq!(jkl.local.len(), 1);
+    assert_eq!(jkl.local[0].start.line, 10);
+    let needless_return = &findings["needless_return"].first_key_value().unwrap().1;
+    assert_eq!(needless_return.in_macro.len(), 1);
+
+    let (status, _findings, _enabled) = scan("fn main() { \"unterminated }", false);
+    assert_eq!(status, FileStatus::Unreadable);
+}
+
+#[test]
+fn test_targets() {
+    let contents = r"
+#![allow(clippy::a)]
+
+#[allow(clippy::b)]
+mod m {
+    #![allow(clippy::c)]
+}
+
+#[allow(clippy::d)]
+impl S {
+    #[allow(clippy::e)]
+    fn f() {
+        #![allow(clippy::f)]
+        #[allow(clippy::g)]
+        let _ = 1;
+        #[allow(clippy::h)]
+        g();
+        let _ = #[allow(clippy::i)] {};
+    }
+}
+
+#[allow(clippy::j)]
+enum E {
+    #[allow(clippy::k)]
+    V,
+}
+
+#[allow(clippy::l)]
+const C: () = ();
+";
+
+    let (findings, _enabled) = visit_source(contents, false);
+    let target = |lint_id: &str| {
+        let locations = findings[lint_id].first_key_value().unwrap().1;
+        *locations.targets.values().next().unwrap()
+    };
+    assert_eq!(target("a"), Target::CrateRoot);
+    assert_eq!(target("b"), Target::Module);
+    assert_eq!(target("c"), Target::Module);
+    assert_eq!(target("d"), Target::Impl);
+    assert_eq!(target("e"), Target::Fn);
+    assert_eq!(target("f"), Target::Fn);
+    assert_eq!(target("g"), Target::Statement);
+    assert_eq!(target("h"), Target::Statement);
+    assert_eq!(target("i"), Target::Expression);
+    assert_eq!(target("j"), Target::Type);
+    assert_eq!(target("k"), Target::Field);
+    assert_eq!(target("l"), Target::Other);
+}
+
+#[test]
+fn test_target_kinds() {
+    let contents = r#"
+[package]
+name = "test"
+build = "build/main.rs"
+
+[[bin]]
+name = "cli"
+path = "cli/main.rs"
+
+[[test]]
+name = "integration"
+path = "testsuite/main.rs"
+"#;
+
+    let manifest = manifest::parse(contents).unwrap();
+    let targets = Targets::new(manifest.targets, true, manifest.build_script);
+    let kind = |path: &str| targets.kind(Path::new(path));
+    assert_eq!(kind("src/lib.rs"), TargetKind::Lib);
+    assert_eq!(kind("src/parse.rs"), TargetKind::Lib);
+    assert_eq!(kind("src/bin/tool.rs"), TargetKind::Bin);
+    assert_eq!(kind("cli/main.rs"), TargetKind::Bin);
+    assert_eq!(kind("cli/args.rs"), TargetKind::Bin);
+    assert_eq!(kind("tests/test.rs"), TargetKind::Test);
+    assert_eq!(kind("testsuite/util.rs"), TargetKind::Test);
+    assert_eq!(kind("examples/demo.rs"), TargetKind::Example);
+    assert_eq!(kind("benches/bench.rs"), TargetKind::Bench);
+    assert_eq!(kind("build/main.rs"), TargetKind::BuildScript);
+    assert_eq!(kind("build.rs"), TargetKind::BuildScript);
+    assert_eq!(kind("fuzz/fuzz_targets/f.rs"), TargetKind::Other);
+
+    let targets = Targets::new(Vec::new(), false, true);
+    assert_eq!(targets.kind(Path::new("src/util.rs")), TargetKind::Bin);
+}
+
+#[test]
+fn test_cfg_test() {
+    let input = quote! {
+        #[allow(clippy::a)]
+        fn f() {}
+
+        #[cfg(test)]
+        mod tests {
+            #[allow(clippy::b)]
+            fn f() {}
+        }
+
+        #[cfg(all(test, feature = "std"))]
+        #[allow(clippy::c)]
+        fn g() {}
+
+        #[cfg(not(test))]
+        #[allow(clippy::d)]
+        fn h() {}
+
+        fn i(x: u8) {
+            match x {
+                #[cfg(test)]
+                0 => {}
+                #[allow(clippy::e)]
+                _ => {}
+            }
+        }
+    };
+
+    let (findings, _enabled) = visit(&input);
+    let cfg_test = |lint_id: &str| {
+        let locations = findings[lint_id].first_key_value().unwrap().1;
+        !locations.cfg_test.is_empty()
+    };
+    assert!(!cfg_test("a"));
+    assert!(cfg_test("b"));
+    assert!(cfg_test("c"));
+    assert!(!cfg_test("d"));
+    assert!(!cfg_test("e"));
+}
+
+#[test]
+fn test_generated() {
+    let bindgen =
+        "/* automatically generated by rust-bindgen 0.69.4 */\n\n#![allow(clippy::all)]\n";
+    let prost = "// This file is @generated by prost-build.\n";
+    let handwritten = "//! Parser.\n\nfn main() {\n    // do not edit\n}\n";
+    assert!(generated::is_generated_file(
+        Path::new("src/bindings.rs"),
+        bindgen
+    ));
+    assert!(generated::is_generated_file(
+        Path::new("src/proto.rs"),
+        prost
+    ));
+    assert!(!generated::is_generated_file(
+        Path::new("src/parser.rs"),
+        handwritten
+    ));
+    let out_dir = Path::new("target/debug/build/foo-0123456789abcdef/out/bindings.rs");
+    assert!(generated::is_generated_file(out_dir, ""));
+
+    let input = quote! {
+        #[allow(clippy::a)]
+        mod proto {
+            include!(concat!(env!("OUT_DIR"), "/proto.rs"));
+        }
+
+        #[allow(clippy::b)]
+        mod handwritten {}
+    };
+
+    let (findings, _enabled) = visit(&input);
+    let generated = |lint_id: &str| {
+        let locations = findings[lint_id].first_key_value().unwrap().1;
+        !locations.generated.is_empty()
+    };
+    assert!(generated("a"));
+    assert!(!generated("b"));
+
+    // Handwritten code next to the include of build script output.
+    let input = quote! {
+        #![allow(clippy::c)]
+
+        include!(concat!(env!("OUT_DIR"), "/version.rs"));
+
+        pub fn version() -> &'static str {
+            VERSION
+        }
+    };
+
+    let (findings, _enabled) = visit(&input);
+    let locations = findings["c"].first_key_value().unwrap().1;
+    assert_eq!(locations.global.len(), 1);
+    assert!(locations.generated.is_empty());
+}
+
+#[test]
+fn test_module_tree() {
+    let mut files = Map::new();
+    files.insert(
+        "src/lib.rs",
+        "mod a; #[path = \"other/b.rs\"] mod b; mod inline { mod c; } fn f() {}",
+    );
+    files.insert("src/a.rs", "mod d; struct S;");
+    files.insert("src/a/d.rs", "fn g() {} fn h() {}");
+    files.insert("src/other/b.rs", "");
+    files.insert("src/inline/c.rs", "impl S { fn f() {} }");
+    files.insert("src/old.rs", "fn unused() {}");
+    let lib = syn::parse_file(files["src/lib.rs"]).unwrap();
+    let outlines: Map<PathBuf, Outline> = files
+        .into_iter()
+        .map(|(path, contents)| (PathBuf::from(path), Outline::parse(contents)))
+        .collect();
+    let targets = Targets::new(Vec::new(), true, true);
+    let modules = ModuleTree::resolve(targets.roots(outlines.keys()), outlines);
+
+    assert!(modules.is_root(Path::new("src/lib.rs")));
+    for live in [
+        "src/a.rs",
+        "src/a/d.rs",
+        "src/other/b.rs",
+        "src/inline/c.rs",
+    ] {
+        assert!(modules.is_live(Path::new(live)), "{}", live);
+    }
+    assert!(!modules.is_live(Path::new("src/old.rs")));
+
+    let descendants = modules.descendants(Path::new("src/lib.rs"));
+    assert_eq!(descendants, Governed { files: 4, items: 6 });
+    assert_eq!(
+        modules.all(),
+        Governed {
+            files: 5,
+            items: 11
+        }
+    );
+
+    let syn::Item::Mod(a) = &lib.items[0] else {
+        unreachable!();
+    };
+    let declared = modules.declared_by(Path::new("src/lib.rs"), &[], a);
+    assert_eq!(declared, Governed { files: 2, items: 4 });
+
+    // Files only compiled for tests.
+    let mut files = Map::new();
+    files.insert(
+        "src/lib.rs",
+        "#[cfg(test)] mod tests; #[cfg(test)] mod inline { mod a; } mod b;",
+    );
+    files.insert("src/tests.rs", "mod helpers;");
+    files.insert("src/tests/helpers.rs", "");
+    files.insert("src/inline/a.rs", "");
+    files.insert("src/b.rs", "");
+    let outlines: Map<PathBuf, Outline> = files
+        .into_iter()
+        .map(|(path, contents)| (PathBuf::from(path), Outline::parse(contents)))
+        .collect();
+    let modules = ModuleTree::resolve(targets.roots(outlines.keys()), outlines);
+    for cfg_test in ["src/tests.rs", "src/tests/helpers.rs", "src/inline/a.rs"] {
+        assert!(modules.is_cfg_test(Path::new(cfg_test)), "{}", cfg_test);
+    }
+    for non_test in ["src/lib.rs", "src/b.rs"] {
+        assert!(!modules.is_cfg_test(Path::new(non_test)), "{}", non_test);
+    }
+
+    // Targets that Cargo discovers in a directory of their own.
+    let mut files = Map::new();
+    for dir in ["src/bin/tool", "examples/demo", "tests/it", "benches/bench"] {
+        files.insert(format!("{}/main.rs", dir), "mod util;");
+        files.insert(format!("{}/util.rs", dir), "fn f() {}");
+    }
+    files.insert("build.rs".to_owned(), "fn main() {}");
+    let paths = files.keys().map(PathBuf::from).collect::<Vec<_>>();
+    let outlines = |files: &Map<String, &str>| {
+        files
+            .iter()
+            .map(|(path, contents)| (PathBuf::from(path), Outline::parse(contents)))
+            .collect::<Map<_, _>>()
+    };
+    let targets = Targets::new(Vec::new(), false, true);
+    let modules = ModuleTree::resolve(targets.roots(&paths), outlines(&files));
+    for path in &paths {
+        assert!(modules.is_live(path), "{}", path.display());
+    }
+
+    // build = false
+    let targets = Targets::new(Vec::new(), false, false);
+    let modules = ModuleTree::resolve(targets.roots(&paths), outlines(&files));
+    assert!(!modules.is_live(Path::new("build.rs")));
+    assert_eq!(targets.kind(Path::new("build.rs")), TargetKind::Other);
+}
+
+#[test]
+fn test_doc_examples() {
+    let contents = r#"
+//! ```
+//! # #![allow(clippy::asdf)]
+//! fn main() {}
+//! ```
+//!
+//! ```text
+//! #[allow(clippy::jkl)]
+//! ```
+
+/// Example:
+///
+/// ```no_run
+///     #[allow(clippy::asdf)]
+/// let _ = 1;
+/// ```
+#[doc = include_str!("../README.md")]
+pub fn f() {}
+"#;
+
+    let (status, findings, _enabled) = scan(contents, false);
+    assert_eq!(status, FileStatus::Parsed);
+    let findings = &findings[&Namespace::Clippy];
+    assert!(!findings.contains_key("jkl"));
+    let locations = findings["asdf"].values().next().unwrap();
+    assert!(locations.global.is_empty() && locations.local.is_empty());
+    let spans = locations
+        .doc_example
+        .iter()
+        .map(|span| {
+            let line = contents.lines().nth(span.start.line - 1).unwrap();
+            let text = line
+                .chars()
+                .skip(span.start.column)
+                .take(span.end.column - span.start.column)
+                .collect::<String>();
+            (span.start.line, text)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        [
+            (3, "clippy::asdf".to_owned()),
+            (14, "clippy::asdf".to_owned())
+        ],
+    );
+
+    let blocks = doc::markdown_file_blocks("# Title\n\n```rust\n#[allow(clippy::jkl)]\n```\n");
+    assert_eq!(blocks.len(), 1);
+    assert_eq!(blocks[0].code, "#[allow(clippy::jkl)]\n");
+}
+
+#[test]
+fn test_command_line_flags() {
+    let config = r#"
+[build]
+rustflags = ["-A", "clippy::asdf", "-Dwarnings"]
+
+[target.'cfg(all())']
+rustflags = ["--allow=clippy::needless-return", "-W", "not_a_lint", "-C", "opt-level=2"]
+# rustflags = ["-A", "clippy::commented_out"]
+rustdocflags = ["--cfg=a#b", "-A", "rustdoc::qwer"] # -A clippy::trailing
+"#;
+    let workflow = r"
+    - run: cargo clippy -- -A clippy::jkl --deny unsafe_code
+    - run: grep -A 3 x
+    # - run: cargo clippy -- -A clippy::disabled_step
+";
+
+    let rustc_lint_list = Namespace::Rustc.lint_list().unwrap();
+    let rustc_lints = rustc_lint_list
+        .iter()
+        .map(|lint| (lint.id.as_str(), lint))
+        .collect::<Map<_, _>>();
+
+    let parse = |contents: &str| {
+        flags::parse(contents, &rustc_lints)
+            .into_iter()
+            .map(|flag| {
+                let line = contents.lines().nth(flag.lint.span.start.line - 1).unwrap();
+                let name = &line[flag.lint.span.start.column..flag.lint.span.end.column];
+                (
+                    flag.lint.level,
+                    flag.lint.namespace,
+                    flag.lint.lint_id,
+                    name.to_owned(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert!(flags::is_flags_file(Path::new(".cargo/config.toml")));
+    assert!(flags::is_flags_file(Path::new(".github/workflows/ci.yml")));
+    assert!(!flags::is_flags_file(Path::new("src/config.toml")));
+    assert!(!flags::is_flags_file(Path::new("build.rs")));
+    assert_eq!(
+        parse(config),
+        [
+            (
+                Level::Allow,
+                Namespace::Clippy,
+                "asdf".to_owned(),
+                "clippy::asdf".to_owned()
+            ),
+            (
+                Level::Deny,
+                Namespace::Rustc,
+                "warnings".to_owned(),
+                "warnings".to_owned()
+            ),
+            (
+                Level::Allow,
+                Namespace::Clippy,
+                "needless_return".to_owned(),
+                "clippy::needless-return".to_owned(),
+            ),
+            (
+                Level::Allow,
+                Namespace::Rustdoc,
+                "qwer".to_owned(),
+                "rustdoc::qwer".to_owned(),
+            ),
+        ],
+    );
+    assert_eq!(
+        parse(workflow),
+        [
+            (
+                Level::Allow,
+                Namespace::Clippy,
+                "jkl".to_owned(),
+                "clippy::jkl".to_owned()
+            ),
+            (
+                Level::Deny,
+                Namespace::Rustc,
+                "unsafe_code".to_owned(),
+                "unsafe_code".to_owned()
+            ),
+        ],
+    );
+}
+
+#[test]
+fn test_opt_outs() {
+    let input = quote! {
+        #![warn(clippy::pedantic)]
+
+        #[allow(clippy::jkl, clippy::asdf)]
+        fn main() {}
+    };
+
+    let (findings, enabled) = visit(&input);
+    let jkl = Lint {
+        id: "jkl".to_owned(),
+        group: LintGroup::Pedantic,
+        level: LintLevel::Allow,
+        former_ids: Set::new(),
+    };
+    let asdf = Lint {
+        id: "asdf".to_owned(),
+        group: LintGroup::Style,
+        level: LintLevel::Warn,
+        former_ids: Set::new(),
+    };
+    let mut lints = Map::new();
+    lints.insert("jkl", &jkl);
+    lints.insert("asdf", &asdf);
+
+    let opt_outs = crate::opt_outs(&findings, &enabled, &lints);
+    assert_eq!(opt_outs.len(), 1);
+    assert_eq!(opt_outs[0].lint_id, "jkl");
+    assert_eq!((opt_outs[0].opted_in, opt_outs[0].turned_off), (1, 1));
+}
+
+#[test]
+fn test_msrv_shims() {
+    let input = quote! {
+        #[allow(unknown_lints, clippy::asdf)]
+        fn f() {}
+
+        #[allow(renamed_and_removed_lints)]
+        #[allow(clippy::jkl)]
+        mod m {
+            #[allow(clippy::needless_return)]
+            fn g() {}
+        }
+
+        #[allow(clippy::asdf)]
+        fn h() {}
+    };
+
+    let (findings, _enabled) = visit(&input);
+    let shims = |lint_id: &str| {
+        findings[lint_id]
+            .values()
+            .flat_map(|loc| loc.shims.values().copied())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(shims("asdf"), [Shim::UnknownLints]);
+    assert_eq!(shims("jkl"), [Shim::RenamedAndRemovedLints]);
+    assert_eq!(shims("needless_return"), [Shim::RenamedAndRemovedLints]);
+}
+
+#[test]
+fn test_former_ids() {
+    let input = quote! {
+        #[allow(clippy::old_return)]
+        fn f() {}
+
+        #[allow(clippy::needless_return)]
+        fn g() {}
+    };
+
+    let (findings, _enabled) = visit(&input);
+    assert!(!findings.contains_key("old_return"));
+    let locations = findings["needless_return"].values().next().unwrap();
+    assert_eq!(locations.local.len(), 2);
+    let former_ids = locations.former_ids.values().collect::<Vec<_>>();
+    assert_eq!(former_ids, ["old_return"]);
+}
+
+#[test]
+fn test_history() {
+    let krate = Crate::new("test".to_owned());
+    let versions = [
+        ("2023-02-01", "0.1.0", false),
+        ("2023-03-01", "0.2.0", true),
+        ("2023-08-01", "0.3.0", true),
+        ("2023-11-01", "0.4.0", false),
+    ];
+    let mut suppressed = Map::new();
+    let mut published = Vec::new();
+    for (date, version, suppresses) in versions {
+        let version = Version::parse(version).unwrap();
+        published.push((date.parse::<Date>().unwrap(), version.clone()));
+        if suppresses {
+            suppressed
+                .entry(krate.clone())
+                .or_insert_with(Set::new)
+                .insert(version);
+        }
+    }
+    let mut crates = Map::new();
+    crates.insert(krate.clone(), published);
+
+    let quarter = |year, quarter| Quarter { year, quarter };
+    let row = |quarter, suppressing, appeared, disappeared| QuarterRow {
+        quarter,
+        suppressing,
+        appeared,
+        disappeared,
+    };
+    assert_eq!(
+        history::quarterly(&suppressed, &crates),
+        [
+            row(quarter(2023, 1), 1, 1, 0),
+            row(quarter(2023, 2), 1, 0, 0),
+            row(quarter(2023, 3), 1, 0, 0),
+            row(quarter(2023, 4), 0, 0, 1),
+        ],
+    );
+    assert_eq!(
+        "2015-05-15 12:34:56.789+00"
+            .parse::<Date>()
+            .unwrap()
+            .quarter(),
+        quarter(2015, 2),
+    );
+
+    // Only the crate version is kept of a suppression in history mode, once
+    // the kinds of code that were not asked for are left out.
+    let opt = Opt::parse_from([
+        "noisy-clippy",
+        "crates",
+        "--history",
+        "--db-dump",
+        "db-dump",
+        "--exclude-cfg-test",
+    ]);
+    let scanned = Collected::default();
+    let contents = Arc::new(String::new());
+    let span = |line| Span {
+        start: LineColumn { line, column: 0 },
+        end: LineColumn { line, column: 1 },
+    };
+    for (lint_id, cfg_test) in [("jkl", false), ("asdf", true)] {
+        let mut locations = Locations::new(&contents);
+        locations.local.push(span(1));
+        if cfg_test {
+            locations.cfg_test.insert(span(1));
+        }
+        let source_file = SourceFile {
+            krate: krate.clone(),
+            version: Version::new(0, 2, 0),
+            relative_path: PathBuf::from("src/lib.rs"),
+            target_kind: TargetKind::Lib,
+        };
+        scanned
+            .findings
+            .lock()
+            .entry(Namespace::Clippy)
+            .or_insert_with(Map::new)
+            .entry(lint_id.to_owned())
+            .or_insert_with(Map::new)
+            .insert(source_file, locations);
+    }
+    let collected = Collected::default();
+    collected.add_history(scanned, &opt, &[]);
+    assert!(collected.findings.lock().is_empty());
+    let history = collected.history.into_inner();
+    let history = &history[&Namespace::Clippy];
+    assert_eq!(history.keys().collect::<Vec<_>>(), ["jkl"]);
+    assert_eq!(
+        history["jkl"][&krate].iter().collect::<Vec<_>>(),
+        [&Version::new(0, 2, 0)],
+    );
+}
+
+#[test]
+fn test_select() {
+    let release = |date: &str, yanked| VersionInfo {
+        created_at: Some(date.parse().unwrap()),
+        yanked: Some(yanked),
+    };
+    let versions = [
+        ("1.9.3", release("2024-01-10", false)),
+        ("1.9.4", release("2024-03-10", true)),
+        ("2.0.0-alpha.1", release("2024-02-10", false)),
+        ("2.0.0-alpha.2", VersionInfo::default()),
+    ]
+    .map(|(version, info)| (Version::parse(version).unwrap(), info));
+    let eligible = |policy, as_of: Option<&str>| {
+        let versions = versions.iter().map(|(version, info)| (version, *info));
+        let as_of = as_of.map(|date| date.parse().unwrap());
+        select::eligible(versions, policy, as_of)
+            .into_iter()
+            .map(Version::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(eligible(Policy::Max, None).last().unwrap(), "2.0.0-alpha.2");
+    // The newest stable release, 1.9.4, is yanked.
+    assert_eq!(eligible(Policy::Stable, None), ["1.9.3"]);
+    assert_eq!(
+        eligible(Policy::NonYanked, None),
+        ["1.9.3", "2.0.0-alpha.1", "2.0.0-alpha.2"],
+    );
+    assert_eq!(
+        eligible(Policy::Max, Some("2024-02-29")),
+        ["1.9.3", "2.0.0-alpha.1"],
+    );
+    assert_eq!(eligible(Policy::Stable, Some("2023-12-31")), [""; 0]);
+}
+
+#[test]
+fn test_index() {
+    let git = br#"{"name":"Serde_Thing","vers":"1.0.0","deps":[{"name":"alloc-stuff","optional":true},{"name":"log","optional":true}],"cksum":"2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824","features":{"std":[],"logging":["dep:log"]},"yanked":false}
+{"name":"Serde_Thing","vers":"1.0.1","deps":[],"cksum":"00","features":{},"features2":{"nightly":[]},"yanked":true}
+"#;
+    let entries = index::parse_entries(git).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name, "Serde_Thing");
+    assert!(entries[1].yanked);
+    assert_eq!(
+        entries[0].feature_names().into_iter().collect::<Vec<_>>(),
+        ["alloc-stuff", "logging", "std"],
+    );
+    assert_eq!(
+        entries[1].feature_names().into_iter().collect::<Vec<_>>(),
+        ["nightly"],
+    );
+    entries[0].verify(b"hello").unwrap();
+    assert!(entries[1].verify(b"hello").is_err());
+
+    // Cargo's cache of the sparse index.
+    let mut sparse = vec![3, 2, 0, 0, 0];
+    sparse.extend_from_slice(b"etag: \"abc\"\0");
+    for line in git.split(|b| *b == b'\n').filter(|line| !line.is_empty()) {
+        let entry: IndexEntry = serde_json::from_slice(line).unwrap();
+        sparse.extend_from_slice(entry.vers.to_string().as_bytes());
+        sparse.push(0);
+        sparse.extend_from_slice(line);
+        sparse.push(0);
+    }
+    let entries = index::parse_entries(&sparse).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].vers, Version::new(1, 0, 1));
+
+    assert_eq!(
+        index::relative_path("serde_thing"),
+        Path::new("se/rd/serde_thing"),
+    );
+    assert_eq!(index::relative_path("syn"), Path::new("3/s/syn"));
+
+    // A lint allowed only under a feature that the crate does not have is
+    // never allowed.
+    let features = entries[0].feature_names();
+    let clippy_lints = Map::new();
+    let cfg_attr = |features, input: &str| {
+        let parser = |input: ParseStream| parse::cfg_attr(input, &clippy_lints, features);
+        parser
+            .parse_str(input)
+            .unwrap()
+            .into_iter()
+            .map(|lint| lint.lint_id)
+            .collect::<Vec<_>>()
+    };
+    let input = r#"feature = "unstable", allow(clippy::asdf)"#;
+    assert_eq!(cfg_attr(None, input), ["asdf"]);
+    assert_eq!(cfg_attr(Some(&features), input), [""; 0]);
+    let input = r#"any(feature = "std", feature = "unstable"), allow(clippy::asdf)"#;
+    assert_eq!(cfg_attr(Some(&features), input), ["asdf"]);
+}
+
+#[test]
+fn test_weights() {
+    let contents = Arc::new(String::new());
+    let span = |line| Span {
+        start: LineColumn { line, column: 0 },
+        end: LineColumn { line, column: 1 },
+    };
+    let mut findings = Map::new();
+    for (krate, relative_path, lines) in [
+        ("serde", "src/de.rs", 1..3),
+        ("serde", "src/ser.rs", 1..2),
+        ("hello", "src/main.rs", 1..2),
+        ("unknown", "src/lib.rs", 1..2),
+        ("empty", "src/lib.rs", 1..1),
+    ] {
+        let mut locations = Locations::new(&contents);
+        locations.local.extend(lines.map(span));
+        let source_file = SourceFile {
+            krate: Crate::new(krate.to_owned()),
+            version: Version::new(1, 0, 0),
+            relative_path: PathBuf::from(relative_path),
+            target_kind: TargetKind::Lib,
+        };
+        findings.insert(source_file, locations);
+    }
+    let mut popularity = Map::new();
+    for (krate, downloads, dependents) in [
+        ("serde", 500_000_000, 40_000),
+        ("hello", 100, 0),
+        ("empty", 1_000, 10),
+    ] {
+        let popularity_entry = Popularity {
+            downloads,
+            dependents,
+        };
+        popularity.insert(Crate::new(krate.to_owned()), popularity_entry);
+    }
+
+    let weight = weight::weigh(&findings, &popularity);
+    assert_eq!(
+        weight,
+        Weight {
+            suppressions: 5,
+            crates: 3,
+            downloads: 500_000_100,
+            dependents: 40_000,
+        },
+    );
+    assert_eq!(weight.key(SortBy::Suppressions), (5, 5));
+    assert_eq!(weight.key(SortBy::Crates), (3, 5));
+    assert_eq!(weight.key(SortBy::Downloads), (500_000_100, 5));
+    assert_eq!(weight.key(SortBy::Dependents), (40_000, 5));
+}
+
+#[test]
+fn test_lockfile() {
+    let cargo_lock = r#"
+version = 4
+
+[[package]]
+name = "myapp"
+version = "0.1.0"
+dependencies = ["serde", "syn 1.0.109", "syn 2.0.100"]
+
+[[package]]
+name = "serde"
+version = "1.0.219"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "5f0e2c6ed6606019b4e29e69dbaba95b11854410e5347d525002456dbbb786b6"
+
+[[package]]
+name = "syn"
+version = "1.0.109"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "syn"
+version = "2.0.100"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "forked"
+version = "0.1.0"
+source = "git+https://github.com/example/forked#0123456789abcdef"
+"#;
+    let metadata = r#"{
+        "packages": [
+            {"name": "myapp", "version": "0.1.0", "source": null},
+            {"name": "serde", "version": "1.0.219", "source": "registry+https://github.com/rust-lang/crates.io-index"},
+            {"name": "syn", "version": "1.0.109", "source": "sparse+https://index.crates.io/"},
+            {"name": "syn", "version": "2.0.100", "source": "registry+https://github.com/rust-lang/crates.io-index"}
+        ],
+        "workspace_members": [],
+        "version": 1
+    }"#;
+
+    for contents in [cargo_lock, metadata] {
+        let crates = lockfile::parse(contents)
+            .unwrap()
+            .into_iter()
+            .map(|(krate, version)| format!("{} {}", krate, version))
+            .collect::<Vec<_>>();
+        assert_eq!(crates, ["serde 1.0.219", "syn 1.0.109", "syn 2.0.100"]);
+    }
 }
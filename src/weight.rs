@@ -0,0 +1,72 @@
+use crate::dbdump::Popularity;
+use crate::name::Crate;
+use crate::{Locations, SourceFile};
+use clap::ValueEnum;
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+// Order of the lints in each table, most first.
+#[derive(ValueEnum, Copy, Clone, Default, PartialEq, Debug)]
+pub(crate) enum SortBy {
+    /// Number of suppressions.
+    #[default]
+    Suppressions,
+    /// Number of crates with a suppression.
+    Crates,
+    /// Total downloads of the crates with a suppression.
+    Downloads,
+    /// Total reverse dependencies of the crates with a suppression.
+    Dependents,
+}
+
+impl SortBy {
+    // Whether sorting needs the popularity of crates from the db dump.
+    pub(crate) fn needs_popularity(self) -> bool {
+        matches!(self, SortBy::Downloads | SortBy::Dependents)
+    }
+}
+
+// A lint's suppressions, with each crate that suppresses it weighted by how
+// widely used it is.
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub(crate) struct Weight {
+    pub suppressions: usize,
+    pub crates: usize,
+    pub downloads: u64,
+    pub dependents: u64,
+}
+
+impl Weight {
+    pub(crate) fn key(self, sort_by: SortBy) -> (u64, usize) {
+        let primary = match sort_by {
+            SortBy::Suppressions => self.suppressions as u64,
+            SortBy::Crates => self.crates as u64,
+            SortBy::Downloads => self.downloads,
+            SortBy::Dependents => self.dependents,
+        };
+        (primary, self.suppressions)
+    }
+}
+
+// Crates count once no matter how many of their files or versions suppress
+// the lint. Crates missing from the db dump weigh nothing.
+pub(crate) fn weigh(
+    findings: &Map<SourceFile, Locations>,
+    popularity: &Map<Crate, Popularity>,
+) -> Weight {
+    let mut weight = Weight::default();
+    let mut crates = Set::new();
+    for (source_file, locations) in findings {
+        weight.suppressions += locations.len();
+        if locations.len() > 0 {
+            crates.insert(&source_file.krate);
+        }
+    }
+    weight.crates = crates.len();
+    for krate in crates {
+        if let Some(popularity) = popularity.get(krate) {
+            weight.downloads += popularity.downloads;
+            weight.dependents += popularity.dependents;
+        }
+    }
+    weight
+}